@@ -0,0 +1,41 @@
+//! Price schedule for descending (Dutch) auctions.
+//!
+//! The price falls from `start_price` at `start_time` to `floor_price` at
+//! `end_time` and then stays at the floor until someone buys or the seller
+//! cancels. A stepwise schedule holds each price for `step_interval`
+//! seconds and then drops to where the linear schedule would be.
+use {
+    crate::{
+        error::MarketError,
+        state::{DecaySchedule, DutchAuctionOrder},
+    },
+    solana_program::program_error::ProgramError,
+};
+
+/// Price a buyer pays at `now`, rounded up so it never undercuts the schedule.
+pub fn current_price(order: &DutchAuctionOrder, now: u64) -> Result<u64, ProgramError> {
+    if now <= order.start_time {
+        return Ok(order.start_price);
+    }
+    if now >= order.end_time {
+        return Ok(order.floor_price);
+    }
+    let mut elapsed = now - order.start_time;
+    if order.decay == DecaySchedule::Stepwise {
+        elapsed -= elapsed % order.step_interval.max(1);
+    }
+    let duration = order
+        .end_time
+        .checked_sub(order.start_time)
+        .ok_or(MarketError::MathOverflow)?;
+    let price_range = order
+        .start_price
+        .checked_sub(order.floor_price)
+        .ok_or(MarketError::MathOverflow)?;
+    // elapsed < duration, so the drop always stays below the price range
+    let drop = (price_range as u128)
+        .checked_mul(elapsed as u128)
+        .ok_or(MarketError::MathOverflow)?
+        / duration as u128;
+    Ok(order.start_price - drop as u64)
+}
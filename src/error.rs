@@ -22,7 +22,7 @@ pub enum MarketError {
     MinPrice,
     #[error("Not In Secondary Market")]
     PrimarySaleFalse,
-    #[error("Max Time Limit is 7 days")]
+    #[error("Auction Duration Above Marketplace Limit")]
     MaxTimeLimit,
     #[error("Auction Hasn't Ended Yet")]
     AuctionNotEnded,
@@ -34,7 +34,52 @@ pub enum MarketError {
     WrongOwner,
     #[error("Auction Not Ended")]
     CannotCancel,
-    
+    #[error("Fee Cannot Exceed 100%")]
+    InvalidFee,
+    #[error("Arithmetic Overflow")]
+    MathOverflow,
+    #[error("Raffle Winner Not Drawn")]
+    RaffleNotDrawn,
+    #[error("Raffle Winner Already Drawn")]
+    RaffleAlreadyDrawn,
+    #[error("Revealed Seed Does Not Match Commitment")]
+    SeedMismatch,
+    #[error("Raffle Has Ended")]
+    RaffleEnded,
+    #[error("Raffle Hasn't Ended Yet")]
+    RaffleNotEnded,
+    #[error("Randomness Not Fulfilled")]
+    RandomnessNotReady,
+    #[error("Prize Already Claimed")]
+    PrizeAlreadyClaimed,
+    #[error("Raffle Minimum Tickets Not Met")]
+    MinTicketsNotMet,
+    #[error("Raffle Is Not Refundable")]
+    RefundNotAvailable,
+    #[error("Wallet Ticket Limit Reached")]
+    WalletTicketLimit,
+    #[error("Bidding Has Closed")]
+    BiddingClosed,
+    #[error("Not In Reveal Window")]
+    NotInRevealWindow,
+    #[error("Reserve Price Not Met")]
+    ReserveNotMet,
+    #[error("Invalid Bid Increment")]
+    InvalidIncrement,
+    #[error("Auction Hasn't Started Yet")]
+    AuctionNotStarted,
+    #[error("Auction Duration Below Marketplace Limit")]
+    MinTimeLimit,
+    #[error("Token Is Not An NFT")]
+    NotAnNft,
+    #[error("Collection Not Approved By Marketplace")]
+    NotAllowlisted,
+    #[error("Invalid Auction Extension")]
+    InvalidExtension,
+    #[error("Raffle Was Not Drawn In Time")]
+    DrawWindowMissed,
+    #[error("Raffle Prizes Must Share A Collection")]
+    PrizeCollectionMismatch,
 }
 
 impl From<MarketError> for ProgramError {
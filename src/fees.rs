@@ -0,0 +1,171 @@
+//! Integer fee and payout math shared by every settlement path.
+//!
+//! All amounts are widened to `u128` before multiplying so nothing can
+//! overflow mid-calculation. Rounding rules:
+//! - the marketplace fee rounds down, the seller's share is `amount - fee`,
+//!   so `seller + fee == amount` for every input and no lamport is lost;
+//! - creator royalties round down, any dust stays with the seller;
+//! - bid increments round up, so a non-zero percentage never rounds away.
+use {
+    crate::{
+        error::MarketError,
+        state::{
+            AuctionExtension, BidIncrement, IncrementKind, IncrementTier, MAX_INCREMENT_TIERS,
+        },
+    },
+    solana_program::program_error::ProgramError,
+};
+
+pub const MAX_BASIS_POINTS: u16 = 10000;
+/// Default step between bids, 5% of the current bid.
+pub const BID_INCREMENT_BASIS_POINTS: u16 = 500;
+
+/// `amount * basis_points / 10000`, rounded down.
+pub fn fee_amount(amount: u64, basis_points: u16) -> Result<u64, ProgramError> {
+    if basis_points > MAX_BASIS_POINTS {
+        return Err(MarketError::InvalidFee.into());
+    }
+    let fee = (amount as u128)
+        .checked_mul(basis_points as u128)
+        .ok_or(MarketError::MathOverflow)?
+        / MAX_BASIS_POINTS as u128;
+    Ok(fee as u64)
+}
+
+/// Splits an escrowed amount into (seller amount, marketplace fee).
+pub fn split_amount(amount: u64, basis_points: u16) -> Result<(u64, u64), ProgramError> {
+    let fee = fee_amount(amount, basis_points)?;
+    let seller_amount = amount.checked_sub(fee).ok_or(MarketError::MathOverflow)?;
+    Ok((seller_amount, fee))
+}
+
+/// Creator royalty on a sale of `amount`, `seller_fee_basis_points` of the
+/// full price but never more than is left once the marketplace `fee` is out.
+pub fn royalty_amount(
+    amount: u64,
+    fee: u64,
+    seller_fee_basis_points: u16,
+) -> Result<u64, ProgramError> {
+    let available = amount.checked_sub(fee).ok_or(MarketError::MathOverflow)?;
+    Ok(fee_amount(amount, seller_fee_basis_points)?.min(available))
+}
+
+/// A creator's cut of `royalty`, `share` is a percentage as Metaplex stores it.
+pub fn creator_share(royalty: u64, share: u8) -> Result<u64, ProgramError> {
+    if share > 100 {
+        return Err(MarketError::InvalidFee.into());
+    }
+    Ok((royalty as u128 * share as u128 / 100) as u64)
+}
+
+/// `amount * basis_points / 10000`, rounded up.
+pub fn increment_amount(amount: u64, basis_points: u16) -> Result<u64, ProgramError> {
+    let scaled = (amount as u128)
+        .checked_mul(basis_points as u128)
+        .ok_or(MarketError::MathOverflow)?;
+    let mut increment = scaled / MAX_BASIS_POINTS as u128;
+    if scaled % MAX_BASIS_POINTS as u128 != 0 {
+        increment += 1;
+    }
+    if increment > u64::MAX as u128 {
+        return Err(MarketError::MathOverflow.into());
+    }
+    Ok(increment as u64)
+}
+
+/// Increment used by auctions listed without their own rule, 5% of the
+/// current bid.
+pub fn default_increment() -> BidIncrement {
+    BidIncrement {
+        kind: IncrementKind::Percent,
+        basis_points: BID_INCREMENT_BASIS_POINTS,
+        step: 0,
+        tier_count: 0,
+        tiers: [IncrementTier::default(); MAX_INCREMENT_TIERS],
+    }
+}
+
+/// Rejects rules that could never be met or that leave bids unordered:
+/// percentages must be in `1..=10000`, steps non-zero, and tiers start at 0
+/// with strictly rising thresholds and nothing set past `tier_count`.
+pub fn check_increment(rule: &BidIncrement) -> Result<(), ProgramError> {
+    let valid = match rule.kind {
+        IncrementKind::Percent => rule.basis_points != 0 && rule.basis_points <= MAX_BASIS_POINTS,
+        IncrementKind::Absolute => rule.step != 0,
+        IncrementKind::Tiered => {
+            let count = rule.tier_count as usize;
+            let (used, unused) = rule.tiers.split_at(count.min(MAX_INCREMENT_TIERS));
+            count != 0
+                && count <= MAX_INCREMENT_TIERS
+                && used[0].threshold == 0
+                && used.iter().all(|tier| tier.step != 0)
+                && used
+                    .windows(2)
+                    .all(|pair| pair[0].threshold < pair[1].threshold)
+                && unused.iter().all(|tier| *tier == IncrementTier::default())
+        }
+    };
+    if !valid {
+        return Err(MarketError::InvalidIncrement.into());
+    }
+    Ok(())
+}
+
+/// Rejects soft closes that could never trigger or never extend, and one
+/// whose single extension already runs past its own cap.
+pub fn check_extension(extension: &AuctionExtension) -> Result<(), ProgramError> {
+    if extension.window == 0
+        || extension.length == 0
+        || (extension.max_total != 0 && extension.length > extension.max_total)
+    {
+        return Err(MarketError::InvalidExtension.into());
+    }
+    Ok(())
+}
+
+/// Step a bid has to add on top of `current_bid` under `rule`. The first bid
+/// has nothing to step over, so the increment is 0 until a bid exists.
+pub fn bid_increment(rule: &BidIncrement, current_bid: u64) -> Result<u64, ProgramError> {
+    if current_bid == 0 {
+        return Ok(0);
+    }
+    match rule.kind {
+        IncrementKind::Percent => increment_amount(current_bid, rule.basis_points),
+        IncrementKind::Absolute => Ok(rule.step),
+        IncrementKind::Tiered => rule.tiers[..rule.tier_count as usize]
+            .iter()
+            .rev()
+            .find(|tier| tier.threshold <= current_bid)
+            .map(|tier| tier.step)
+            .ok_or_else(|| MarketError::InvalidIncrement.into()),
+    }
+}
+
+/// Lowest bid accepted on top of `current_bid` under `rule`.
+pub fn min_next_bid_for(rule: &BidIncrement, current_bid: u64) -> Result<u64, ProgramError> {
+    current_bid
+        .checked_add(bid_increment(rule, current_bid)?)
+        .ok_or_else(|| MarketError::MathOverflow.into())
+}
+
+/// Resolves a bid with proxy `ceiling` against the leader's `leader_ceiling`.
+/// Returns the new price and whether the bidder takes the lead: a higher
+/// ceiling wins at one increment over the old one (capped at its own
+/// ceiling), otherwise the leader is bid up to one increment over the
+/// challenger's ceiling, and ties go to the earlier bidder.
+pub fn proxy_bid_price(
+    rule: &BidIncrement,
+    leader_ceiling: u64,
+    bid: u64,
+    ceiling: u64,
+) -> Result<(u64, bool), ProgramError> {
+    // saturating is fine here, both prices are capped by a real ceiling
+    if ceiling > leader_ceiling {
+        let step = bid_increment(rule, leader_ceiling)?;
+        let price = leader_ceiling.saturating_add(step).min(ceiling).max(bid);
+        Ok((price, true))
+    } else {
+        let step = bid_increment(rule, ceiling)?;
+        Ok((ceiling.saturating_add(step).min(leader_ceiling), false))
+    }
+}
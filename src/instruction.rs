@@ -1,17 +1,29 @@
 use {
-    solana_program::program_error::ProgramError, spl_token::error::TokenError::InvalidInstruction,
+    crate::state::{
+        AllowlistKind, AuctionExtension, BidIncrement, DecaySchedule, IncrementKind, IncrementTier,
+        RandomnessSource, MAX_INCREMENT_TIERS,
+    },
+    borsh::BorshDeserialize,
+    solana_program::{program_error::ProgramError, pubkey::Pubkey},
+    spl_token::error::TokenError::InvalidInstruction,
     std::convert::TryInto,
 };
 pub enum MarketplaceInstruction {
     AuctionStart {
         minimum_price: u64,
         time: u64,
+        buy_now_price: u64,
+        reserve_commitment: [u8; 32],
+        extension: Option<AuctionExtension>,
+        increment: Option<BidIncrement>,
+        start_time: u64,
     },
     PlaceBid {
         new_bid: u64,
+        max_bid: u64,
     },
     CompleteAuction,
-    CompleteAuctionUserspl,
+    CompleteAuctionUser,
 
     CompleteAuctionAnyTime,
 
@@ -20,87 +32,159 @@ pub enum MarketplaceInstruction {
         price: u64,
         time: u64,
         total_ticket: u64,
+        seed_commitment: [u8; 32],
+        randomness_source: RandomnessSource,
+        randomness_account: Pubkey,
+        prize_count: u8,
+        min_tickets: u64,
+        max_tickets_per_wallet: u64,
     },
     MakeRaffleEntry {
         amount: u64,
         quantity: u8,
     },
-    EndRaffle,
-    AuctionStartSol {
-        minimum_price: u64,
-        time: u64,
+    EndRaffle {
+        prize_index: u8,
     },
-    PlaceBidSol {
-        new_bid: u64,
+    HandleNonTransfer {
+        prize_index: u8,
     },
-    CompleteAuctionSol,
-    CompleteAuctionUserSol,
-    CompleteAuctionAnyTimeSol,
-    CanceAuctionSol,
 
-    HandleNonTransfer,
+    InitializeMarketplace {
+        auction_fee_basis_points: u16,
+        raffle_fee_basis_points: u16,
+        featuring_fee_basis_points: u16,
+    },
+    UpdateFees {
+        auction_fee_basis_points: u16,
+        raffle_fee_basis_points: u16,
+        featuring_fee_basis_points: u16,
+    },
+    DrawRaffle {
+        seed: [u8; 32],
+    },
+    SetRandomnessOracle,
+    SettleRaffleWinners,
+    RefundRaffleTicket,
+    ClaimRaffleProceeds,
+    CancelRaffle,
+    DutchAuctionStart {
+        start_price: u64,
+        floor_price: u64,
+        end_time: u64,
+        decay: DecaySchedule,
+        step_interval: u64,
+    },
+    BuyDutch {
+        max_price: u64,
+    },
+    CancelDutchAuction,
+    SealedAuctionStart {
+        minimum_price: u64,
+        bid_end_time: u64,
+        reveal_end_time: u64,
+        is_second_price: bool,
+    },
+    PlaceSealedBid {
+        commitment: [u8; 32],
+        deposit: u64,
+    },
+    RevealSealedBid {
+        bid: u64,
+        salt: [u8; 32],
+    },
+    CompleteSealedAuction,
+    ReclaimSealedBid,
+    BuyNow,
+    RevealReserve {
+        reserve: u64,
+        salt: [u8; 32],
+    },
+    UpdateAuctionDefaults {
+        extension: AuctionExtension,
+        min_auction_duration: u64,
+        max_auction_duration: u64,
+    },
+    AddAllowlistEntry {
+        kind: AllowlistKind,
+        address: Pubkey,
+    },
+    RemoveAllowlistEntry,
 }
 impl MarketplaceInstruction {
     pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
         let (&tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
         Ok(match tag {
             4 => {
-                let (minimum_price, rest) = rest.split_at(8);
-                let minimum_price = minimum_price
-                    .try_into()
-                    .ok()
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let (time, _rest) = rest.split_at(8);
-                let time = time
-                    .try_into()
-                    .ok()
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                
+                let (minimum_price, rest) = Self::unpack_u64(rest)?;
+                let (time, rest) = Self::unpack_u64(rest)?;
+                let (buy_now_price, rest) = Self::unpack_optional_u64(rest)?;
+                let (reserve_commitment, rest) = Self::unpack_optional_bytes32(rest)?;
+                // optional, the marketplace defaults apply without them
+                let (extension, rest) = if rest.is_empty() {
+                    (None, rest)
+                } else {
+                    let (extension, rest) = Self::unpack_extension(rest)?;
+                    (Some(extension), rest)
+                };
+                let (increment, rest) = if rest.is_empty() {
+                    (None, rest)
+                } else {
+                    let (increment, rest) = Self::unpack_increment(rest)?;
+                    (Some(increment), rest)
+                };
+                // 0 opens bidding right away
+                let (start_time, _rest) = Self::unpack_optional_u64(rest)?;
                 Self::AuctionStart {
                     minimum_price,
                     time,
+                    buy_now_price,
+                    reserve_commitment,
+                    extension,
+                    increment,
+                    start_time,
                 }
             }
             5 => {
-                let (new_bid, _rest) = rest.split_at(8);
-                let new_bid = new_bid
-                    .try_into()
-                    .ok()
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                Self::PlaceBid { new_bid }
+                let (new_bid, rest) = Self::unpack_u64(rest)?;
+                // optional proxy ceiling, a plain bid without it
+                let (max_bid, _rest) = Self::unpack_optional_u64(rest)?;
+                Self::PlaceBid { new_bid, max_bid }
             }
             6 => Self::CompleteAuction,
 
             15 => Self::CompleteAuctionAnyTime,
             7 => Self::CanceAuction,
             9 => {
-                let (minimum_price, rest) = rest.split_at(8);
-                let price = minimum_price
-                    .try_into()
-                    .ok()
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let (time, _rest) = rest.split_at(8);
-                let time = time
-                    .try_into()
-                    .ok()
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let total_ticket = _rest
-                    .try_into()
-                    .ok()
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
+                let (price, rest) = Self::unpack_u64(rest)?;
+                let (time, rest) = Self::unpack_u64(rest)?;
+                let (total_ticket, rest) = Self::unpack_u64(rest)?;
+                let (seed_commitment, rest) = Self::unpack_bytes32(rest)?;
+                let (source, rest) = rest.split_first().ok_or(InvalidInstruction)?;
+                let randomness_source = RandomnessSource::try_from_slice(&[*source])
+                    .map_err(|_| InvalidInstruction)?;
+                let (randomness_account, rest) = Self::unpack_bytes32(rest)?;
+                let randomness_account = Pubkey::new_from_array(randomness_account);
+                let (&prize_count, rest) = rest.split_first().ok_or(InvalidInstruction)?;
+                // optional, raffles listed without them have no threshold or cap
+                let (min_tickets, rest) = Self::unpack_optional_u64(rest)?;
+                let (max_tickets_per_wallet, _rest) = Self::unpack_optional_u64(rest)?;
                 Self::RaffleStart {
                     time,
                     price,
                     total_ticket,
+                    seed_commitment,
+                    randomness_source,
+                    randomness_account,
+                    prize_count,
+                    min_tickets,
+                    max_tickets_per_wallet,
                 }
             }
-            11 => Self::EndRaffle,
+            11 => {
+                let (&prize_index, _rest) = rest.split_first().ok_or(InvalidInstruction)?;
+                Self::EndRaffle { prize_index }
+            }
             13 => {
                 let (new_bid, _rest) = rest.split_at(8);
                 let amount = new_bid
@@ -111,43 +195,220 @@ impl MarketplaceInstruction {
                 let (&quantity, _) = _rest.split_first().ok_or(InvalidInstruction)?;
                 Self::MakeRaffleEntry { amount, quantity }
             }
-            17 => {
-                let (minimum_price, rest) = rest.split_at(8);
-                let minimum_price = minimum_price
-                    .try_into()
-                    .ok()
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let (time, _rest) = rest.split_at(8);
-                let time = time
-                    .try_into()
-                    .ok()
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                Self::AuctionStartSol {
+            29 => Self::CompleteAuctionUser,
+            32 => {
+                let (&prize_index, _rest) = rest.split_first().ok_or(InvalidInstruction)?;
+                Self::HandleNonTransfer { prize_index }
+            }
+            33 => {
+                let (auction_fee_basis_points, rest) = Self::unpack_u16(rest)?;
+                let (raffle_fee_basis_points, rest) = Self::unpack_u16(rest)?;
+                let (featuring_fee_basis_points, _rest) = Self::unpack_u16(rest)?;
+                Self::InitializeMarketplace {
+                    auction_fee_basis_points,
+                    raffle_fee_basis_points,
+                    featuring_fee_basis_points,
+                }
+            }
+            34 => {
+                let (auction_fee_basis_points, rest) = Self::unpack_u16(rest)?;
+                let (raffle_fee_basis_points, rest) = Self::unpack_u16(rest)?;
+                let (featuring_fee_basis_points, _rest) = Self::unpack_u16(rest)?;
+                Self::UpdateFees {
+                    auction_fee_basis_points,
+                    raffle_fee_basis_points,
+                    featuring_fee_basis_points,
+                }
+            }
+            35 => {
+                let (seed, _rest) = Self::unpack_bytes32(rest)?;
+                Self::DrawRaffle { seed }
+            }
+            36 => Self::SetRandomnessOracle,
+            37 => Self::SettleRaffleWinners,
+            38 => Self::RefundRaffleTicket,
+            39 => Self::ClaimRaffleProceeds,
+            40 => Self::CancelRaffle,
+            41 => {
+                let (start_price, rest) = Self::unpack_u64(rest)?;
+                let (floor_price, rest) = Self::unpack_u64(rest)?;
+                let (end_time, rest) = Self::unpack_u64(rest)?;
+                let (decay, rest) = rest.split_first().ok_or(InvalidInstruction)?;
+                let decay =
+                    DecaySchedule::try_from_slice(&[*decay]).map_err(|_| InvalidInstruction)?;
+                let (step_interval, _rest) = Self::unpack_u64(rest)?;
+                Self::DutchAuctionStart {
+                    start_price,
+                    floor_price,
+                    end_time,
+                    decay,
+                    step_interval,
+                }
+            }
+            42 => {
+                let (max_price, _rest) = Self::unpack_u64(rest)?;
+                Self::BuyDutch { max_price }
+            }
+            43 => Self::CancelDutchAuction,
+            44 => {
+                let (minimum_price, rest) = Self::unpack_u64(rest)?;
+                let (bid_end_time, rest) = Self::unpack_u64(rest)?;
+                let (reveal_end_time, rest) = Self::unpack_u64(rest)?;
+                // optional, first-price when absent
+                let is_second_price = match rest.first() {
+                    None | Some(0) => false,
+                    Some(1) => true,
+                    Some(_) => return Err(InvalidInstruction.into()),
+                };
+                Self::SealedAuctionStart {
                     minimum_price,
-                    time,
+                    bid_end_time,
+                    reveal_end_time,
+                    is_second_price,
                 }
             }
-            19 => {
-                let (new_bid, _rest) = rest.split_at(8);
-                let new_bid = new_bid
-                    .try_into()
-                    .ok()
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                Self::PlaceBidSol { new_bid }
+            45 => {
+                let (commitment, rest) = Self::unpack_bytes32(rest)?;
+                let (deposit, _rest) = Self::unpack_u64(rest)?;
+                Self::PlaceSealedBid {
+                    commitment,
+                    deposit,
+                }
+            }
+            46 => {
+                let (bid, rest) = Self::unpack_u64(rest)?;
+                let (salt, _rest) = Self::unpack_bytes32(rest)?;
+                Self::RevealSealedBid { bid, salt }
+            }
+            47 => Self::CompleteSealedAuction,
+            48 => Self::ReclaimSealedBid,
+            49 => Self::BuyNow,
+            53 => {
+                let (extension, rest) = Self::unpack_extension(rest)?;
+                let (min_auction_duration, rest) = Self::unpack_u64(rest)?;
+                let (max_auction_duration, _rest) = Self::unpack_u64(rest)?;
+                Self::UpdateAuctionDefaults {
+                    extension,
+                    min_auction_duration,
+                    max_auction_duration,
+                }
+            }
+            54 => {
+                let (kind, rest) = rest.split_first().ok_or(InvalidInstruction)?;
+                let kind =
+                    AllowlistKind::try_from_slice(&[*kind]).map_err(|_| InvalidInstruction)?;
+                let (address, _rest) = Self::unpack_bytes32(rest)?;
+                Self::AddAllowlistEntry {
+                    kind,
+                    address: Pubkey::new_from_array(address),
+                }
+            }
+            55 => Self::RemoveAllowlistEntry,
+            51 => {
+                let (reserve, rest) = Self::unpack_u64(rest)?;
+                let (salt, _rest) = Self::unpack_bytes32(rest)?;
+                Self::RevealReserve { reserve, salt }
             }
-            21 => Self::CompleteAuctionSol,
-            23 => Self::CanceAuctionSol,
-
-            27 => Self::CompleteAuctionAnyTimeSol,
-            29 => Self::CompleteAuctionUserspl,
-            31 => Self::CompleteAuctionUserSol,
-            32 => Self::HandleNonTransfer,
             _ => return Err(InvalidInstruction.into()),
         })
     }
+    fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
+        if input.len() < 8 {
+            return Err(InvalidInstruction.into());
+        }
+        let (value, rest) = input.split_at(8);
+        let value = value
+            .try_into()
+            .ok()
+            .map(u64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok((value, rest))
+    }
+    fn unpack_extension(input: &[u8]) -> Result<(AuctionExtension, &[u8]), ProgramError> {
+        let (window, rest) = Self::unpack_u64(input)?;
+        let (length, rest) = Self::unpack_u64(rest)?;
+        let (max_total, rest) = Self::unpack_u64(rest)?;
+        Ok((
+            AuctionExtension {
+                window,
+                length,
+                max_total,
+            },
+            rest,
+        ))
+    }
+    // kind byte, then a u16 percentage, a u64 step, or a tier count followed
+    // by (threshold, step) u64 pairs
+    fn unpack_increment(input: &[u8]) -> Result<(BidIncrement, &[u8]), ProgramError> {
+        let (kind, rest) = input.split_first().ok_or(InvalidInstruction)?;
+        let kind = IncrementKind::try_from_slice(&[*kind]).map_err(|_| InvalidInstruction)?;
+        let mut increment = BidIncrement {
+            kind,
+            basis_points: 0,
+            step: 0,
+            tier_count: 0,
+            tiers: [IncrementTier::default(); MAX_INCREMENT_TIERS],
+        };
+        let rest = match kind {
+            IncrementKind::Percent => {
+                let (basis_points, rest) = Self::unpack_u16(rest)?;
+                increment.basis_points = basis_points;
+                rest
+            }
+            IncrementKind::Absolute => {
+                let (step, rest) = Self::unpack_u64(rest)?;
+                increment.step = step;
+                rest
+            }
+            IncrementKind::Tiered => {
+                let (&tier_count, mut rest) = rest.split_first().ok_or(InvalidInstruction)?;
+                if tier_count as usize > MAX_INCREMENT_TIERS {
+                    return Err(InvalidInstruction.into());
+                }
+                for tier in increment.tiers.iter_mut().take(tier_count as usize) {
+                    let (threshold, next) = Self::unpack_u64(rest)?;
+                    let (step, next) = Self::unpack_u64(next)?;
+                    *tier = IncrementTier { threshold, step };
+                    rest = next;
+                }
+                increment.tier_count = tier_count;
+                rest
+            }
+        };
+        Ok((increment, rest))
+    }
+    fn unpack_optional_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
+        if input.is_empty() {
+            return Ok((0, input));
+        }
+        Self::unpack_u64(input)
+    }
+    fn unpack_bytes32(input: &[u8]) -> Result<([u8; 32], &[u8]), ProgramError> {
+        if input.len() < 32 {
+            return Err(InvalidInstruction.into());
+        }
+        let (value, rest) = input.split_at(32);
+        let value = value.try_into().map_err(|_| InvalidInstruction)?;
+        Ok((value, rest))
+    }
+    fn unpack_optional_bytes32(input: &[u8]) -> Result<([u8; 32], &[u8]), ProgramError> {
+        if input.is_empty() {
+            return Ok(([0; 32], input));
+        }
+        Self::unpack_bytes32(input)
+    }
+    fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+        if input.len() < 2 {
+            return Err(InvalidInstruction.into());
+        }
+        let (value, rest) = input.split_at(2);
+        let value = value
+            .try_into()
+            .ok()
+            .map(u16::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok((value, rest))
+    }
     //  fn puffed_out_string(s: &String, size: usize) -> String {
     //     let mut array_of_zeroes = vec![];
     //     let puff_amount = size - s.len();
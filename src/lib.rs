@@ -3,3 +3,6 @@ pub mod processor;
 pub mod entrypoint;
 pub mod state;
 pub mod error;
+pub mod fees;
+pub mod randomness;
+pub mod dutch;
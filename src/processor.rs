@@ -1,28 +1,52 @@
 use std::str::FromStr;
 
-use mpl_token_metadata::state::Metadata;
+use mpl_token_metadata::{pda::find_metadata_account, state::Metadata};
 use solana_program::system_instruction::transfer;
 
-use crate::state::{AuctionOrderSol, FeaturedRaffles};
+use crate::dutch;
+use crate::fees;
+#[cfg(feature = "local-randomness")]
+use crate::randomness::DeterministicProvider;
+use crate::randomness::{self, CommitRevealProvider, OracleProvider, RandomnessProvider};
+use crate::state::{
+    AllowlistEntry, AllowlistKind, AuctionExtension, BidIncrement, BlackMarketInfo, DecaySchedule,
+    DutchAuctionOrder, FeaturedRaffles, PaymentMint, RandomnessSource, SealedAuctionOrder,
+    SealedBid,
+};
 
 use {
-    crate::state::{RaffleCounter, RaffleOrder},
+    crate::state::{RaffleOrder, RafflePrize, RaffleTicket, TicketRange},
     crate::{error::MarketError, instruction::MarketplaceInstruction, state::AuctionOrder},
     borsh::BorshDeserialize,
     borsh::BorshSerialize,
     solana_program::{
         account_info::{next_account_info, AccountInfo},
-        borsh::try_from_slice_unchecked,
+        borsh::{get_instance_packed_len, try_from_slice_unchecked},
+        bpf_loader_upgradeable::{self, UpgradeableLoaderState},
         entrypoint::ProgramResult,
+        hash::hashv,
         program::{invoke, invoke_signed},
         program_error::ProgramError,
         program_pack::Pack,
+        program_utils::limited_deserialize,
         pubkey::Pubkey,
-        sysvar::{clock::Clock, Sysvar},
+        system_instruction,
+        slot_hashes::MAX_ENTRIES,
+        sysvar::{clock::Clock, rent::Rent, Sysvar},
     },
     spl_token::instruction as SPLIX,
     spl_token::state as SPLS,
 };
+// how long a raffle owner has after sales close to fix the draw slot (or the
+// oracle to deliver) before buyers are refunded
+const DRAW_GRACE_PERIOD: u64 = 86400;
+// slots between fixing a commit-reveal draw and the slot hash it is drawn
+// from, far enough ahead that nobody knows the hash when the slot is fixed
+const DRAW_SLOT_DELAY: u64 = 10;
+// how long a seller has after the auction ends to reveal a hidden reserve
+const RESERVE_REVEAL_PERIOD: u64 = 86400;
+// re-draws allowed per prize when the ticket drawn belongs to an earlier winner
+const MAX_DRAW_ATTEMPTS: u64 = 64;
 pub struct Processor {}
 impl Processor {
     pub fn start_process(
@@ -35,9 +59,24 @@ impl Processor {
             MarketplaceInstruction::AuctionStart {
                 minimum_price,
                 time,
-            } => Self::auction_order(program_id, account_info, minimum_price, time),
-            MarketplaceInstruction::PlaceBid { new_bid } => {
-                Self::place_bid(program_id, account_info, new_bid)
+                buy_now_price,
+                reserve_commitment,
+                extension,
+                increment,
+                start_time,
+            } => Self::auction_order(
+                program_id,
+                account_info,
+                minimum_price,
+                time,
+                buy_now_price,
+                reserve_commitment,
+                extension,
+                increment,
+                start_time,
+            ),
+            MarketplaceInstruction::PlaceBid { new_bid, max_bid } => {
+                Self::place_bid(program_id, account_info, new_bid, max_bid)
             }
             MarketplaceInstruction::CompleteAuction => {
                 Self::complete_auction_order(program_id, account_info)
@@ -50,43 +89,458 @@ impl Processor {
                 time,
                 price,
                 total_ticket,
-            } => Self::raffle_start(program_id, account_info, time, price, total_ticket),
-            MarketplaceInstruction::EndRaffle => Self::end_raffle(program_id, account_info),
-            MarketplaceInstruction::MakeRaffleEntry { amount, quantity } => {
-                Self::make_raffle_entry(program_id, account_info, amount, quantity)
-            }
-            MarketplaceInstruction::AuctionStartSol {
-                minimum_price,
+                seed_commitment,
+                randomness_source,
+                randomness_account,
+                prize_count,
+                min_tickets,
+                max_tickets_per_wallet,
+            } => Self::raffle_start(
+                program_id,
+                account_info,
                 time,
-            } => Self::auction_order_sol(program_id, account_info, minimum_price, time),
-            MarketplaceInstruction::PlaceBidSol { new_bid } => {
-                Self::place_bid_sol(program_id, account_info, new_bid)
+                price,
+                total_ticket,
+                seed_commitment,
+                randomness_source,
+                randomness_account,
+                prize_count,
+                min_tickets,
+                max_tickets_per_wallet,
+            ),
+            MarketplaceInstruction::EndRaffle { prize_index } => {
+                Self::end_raffle(program_id, account_info, prize_index as usize)
             }
-            MarketplaceInstruction::CompleteAuctionSol => {
-                Self::complete_auction_order_sol(program_id, account_info)
+            MarketplaceInstruction::MakeRaffleEntry { amount, quantity } => {
+                Self::make_raffle_entry(program_id, account_info, amount, quantity)
             }
-            MarketplaceInstruction::CanceAuctionSol => {
-                Self::cancel_auction_sol(program_id, account_info)
+            MarketplaceInstruction::CompleteAuctionUser => {
+                Self::complete_auction_order_user(program_id, account_info)
             }
-            MarketplaceInstruction::CompleteAuctionAnyTimeSol => {
-                Self::complete_auction_order_sol_anytime(program_id, account_info)
+            MarketplaceInstruction::HandleNonTransfer { prize_index } => {
+                Self::handle_raffle_non_transfers(program_id, account_info, prize_index as usize)
+            }
+            MarketplaceInstruction::InitializeMarketplace {
+                auction_fee_basis_points,
+                raffle_fee_basis_points,
+                featuring_fee_basis_points,
+            } => Self::initialize_marketplace(
+                program_id,
+                account_info,
+                auction_fee_basis_points,
+                raffle_fee_basis_points,
+                featuring_fee_basis_points,
+            ),
+            MarketplaceInstruction::UpdateFees {
+                auction_fee_basis_points,
+                raffle_fee_basis_points,
+                featuring_fee_basis_points,
+            } => Self::update_fees(
+                program_id,
+                account_info,
+                auction_fee_basis_points,
+                raffle_fee_basis_points,
+                featuring_fee_basis_points,
+            ),
+            MarketplaceInstruction::DrawRaffle { seed } => {
+                Self::draw_raffle(program_id, account_info, seed)
+            }
+            MarketplaceInstruction::SetRandomnessOracle => {
+                Self::set_randomness_oracle(program_id, account_info)
+            }
+            MarketplaceInstruction::SettleRaffleWinners => {
+                Self::settle_raffle_winners(program_id, account_info)
+            }
+            MarketplaceInstruction::RefundRaffleTicket => {
+                Self::refund_raffle_ticket(program_id, account_info)
+            }
+            MarketplaceInstruction::ClaimRaffleProceeds => {
+                Self::claim_raffle_proceeds(program_id, account_info)
+            }
+            MarketplaceInstruction::CancelRaffle => Self::cancel_raffle(program_id, account_info),
+            MarketplaceInstruction::DutchAuctionStart {
+                start_price,
+                floor_price,
+                end_time,
+                decay,
+                step_interval,
+            } => Self::dutch_auction_order(
+                program_id,
+                account_info,
+                start_price,
+                floor_price,
+                end_time,
+                decay,
+                step_interval,
+            ),
+            MarketplaceInstruction::BuyDutch { max_price } => {
+                Self::buy_dutch(program_id, account_info, max_price)
             }
-            MarketplaceInstruction::CompleteAuctionUserspl => {
-                Self::complete_auction_order_user(program_id, account_info)
+            MarketplaceInstruction::CancelDutchAuction => {
+                Self::cancel_dutch_auction(program_id, account_info)
             }
-            MarketplaceInstruction::CompleteAuctionUserSol => {
-                Self::complete_auction_order_sol_user(program_id, account_info)
+            MarketplaceInstruction::SealedAuctionStart {
+                minimum_price,
+                bid_end_time,
+                reveal_end_time,
+                is_second_price,
+            } => Self::sealed_auction_order(
+                program_id,
+                account_info,
+                minimum_price,
+                bid_end_time,
+                reveal_end_time,
+                is_second_price,
+            ),
+            MarketplaceInstruction::PlaceSealedBid {
+                commitment,
+                deposit,
+            } => Self::place_sealed_bid(program_id, account_info, commitment, deposit),
+            MarketplaceInstruction::RevealSealedBid { bid, salt } => {
+                Self::reveal_sealed_bid(program_id, account_info, bid, salt)
+            }
+            MarketplaceInstruction::CompleteSealedAuction => {
+                Self::complete_sealed_auction(program_id, account_info)
+            }
+            MarketplaceInstruction::ReclaimSealedBid => {
+                Self::reclaim_sealed_bid(program_id, account_info)
+            }
+            MarketplaceInstruction::BuyNow => Self::buy_now(program_id, account_info),
+            MarketplaceInstruction::RevealReserve { reserve, salt } => {
+                Self::reveal_reserve(program_id, account_info, reserve, salt)
+            }
+            MarketplaceInstruction::UpdateAuctionDefaults {
+                extension,
+                min_auction_duration,
+                max_auction_duration,
+            } => Self::update_auction_defaults(
+                program_id,
+                account_info,
+                extension,
+                min_auction_duration,
+                max_auction_duration,
+            ),
+            MarketplaceInstruction::AddAllowlistEntry { kind, address } => {
+                Self::add_allowlist_entry(program_id, account_info, kind, address)
             }
-            MarketplaceInstruction::HandleNonTransfer => {
-                Self::handle_raffle_non_transfers(program_id, account_info)
+            MarketplaceInstruction::RemoveAllowlistEntry => {
+                Self::remove_allowlist_entry(program_id, account_info)
             }
         }
     }
+    //marketplace config
+    fn initialize_marketplace(
+        program_id: Pubkey,
+        account_info: &[AccountInfo],
+        auction_fee_basis_points: u16,
+        raffle_fee_basis_points: u16,
+        featuring_fee_basis_points: u16,
+    ) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let admin_info = next_account_info(accounts)?; // upgrade authority, pays for the config account
+        let market_info_account = next_account_info(accounts)?; // config pda to be created
+        let treasury_info = next_account_info(accounts)?; // receives sol fees
+        let spl_treasury_info = next_account_info(accounts)?; // owner of the spl fee token accounts
+        let program_data_info = next_account_info(accounts)?; // programdata account of this program
+        let sys_program_info = next_account_info(accounts)?;
+        if !admin_info.is_signer {
+            return Err(MarketError::WrongOwner.into());
+        }
+        Self::check_fees(
+            auction_fee_basis_points,
+            raffle_fee_basis_points,
+            featuring_fee_basis_points,
+        )?;
+        // only the upgrade authority may claim the admin seat, otherwise the
+        // first caller after deployment could take over the marketplace
+        let (program_data_address, _program_data_nonce) = Pubkey::find_program_address(
+            &[program_id.as_ref()],
+            &bpf_loader_upgradeable::id(),
+        );
+        if *program_data_info.key != program_data_address {
+            return Err(MarketError::PdaError.into());
+        }
+        let program_data_len = UpgradeableLoaderState::programdata_data_offset()
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        match limited_deserialize(&program_data_info.data.borrow(), program_data_len as u64)
+            .map_err(|_| ProgramError::InvalidAccountData)?
+        {
+            UpgradeableLoaderState::ProgramData {
+                upgrade_authority_address: Some(authority),
+                ..
+            } if authority == *admin_info.key => {}
+            _ => return Err(MarketError::WrongOwner.into()),
+        }
+        let (market_info_pda, _nonce) =
+            Pubkey::find_program_address(&[b"black_market_info"], &program_id);
+        if *market_info_account.key != market_info_pda {
+            return Err(MarketError::PdaError.into());
+        }
+        let market_info = BlackMarketInfo {
+            is_initialized: true,
+            owner_wallet_address: *admin_info.key,
+            treasury_wallet_address: *treasury_info.key,
+            spl_treasury_wallet_address: *spl_treasury_info.key,
+            auction_fee_basis_points,
+            raffle_fee_basis_points,
+            featuring_fee_basis_points,
+            randomness_oracle_program: Pubkey::default(),
+            default_extension: AuctionExtension {
+                window: 120,
+                length: 120,
+                max_total: 0,
+            },
+            min_auction_duration: 0,
+            max_auction_duration: 604800,
+        };
+        let space = get_instance_packed_len(&market_info)?;
+        invoke_signed(
+            &system_instruction::create_account(
+                admin_info.key,
+                market_info_account.key,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                &program_id,
+            ),
+            &[
+                admin_info.clone(),
+                market_info_account.clone(),
+                sys_program_info.clone(),
+            ],
+            &[&[&b"black_market_info"[..], &[_nonce]]],
+        )?;
+        market_info.serialize(&mut &mut market_info_account.data.borrow_mut()[..])?;
+        Ok(())
+    }
+    fn update_fees(
+        program_id: Pubkey,
+        account_info: &[AccountInfo],
+        auction_fee_basis_points: u16,
+        raffle_fee_basis_points: u16,
+        featuring_fee_basis_points: u16,
+    ) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let admin_info = next_account_info(accounts)?;
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        let mut market_info = Self::load_market_info(&program_id, market_info_account)?;
+        if !admin_info.is_signer || *admin_info.key != market_info.owner_wallet_address {
+            return Err(MarketError::WrongOwner.into());
+        }
+        Self::check_fees(
+            auction_fee_basis_points,
+            raffle_fee_basis_points,
+            featuring_fee_basis_points,
+        )?;
+        market_info.auction_fee_basis_points = auction_fee_basis_points;
+        market_info.raffle_fee_basis_points = raffle_fee_basis_points;
+        market_info.featuring_fee_basis_points = featuring_fee_basis_points;
+        market_info.serialize(&mut &mut market_info_account.data.borrow_mut()[..])?;
+        Ok(())
+    }
+    fn set_randomness_oracle(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let admin_info = next_account_info(accounts)?;
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        let oracle_program_info = next_account_info(accounts)?; // oracle writing OracleRandomness accounts
+        let mut market_info = Self::load_market_info(&program_id, market_info_account)?;
+        if !admin_info.is_signer || *admin_info.key != market_info.owner_wallet_address {
+            return Err(MarketError::WrongOwner.into());
+        }
+        if !oracle_program_info.executable {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        market_info.randomness_oracle_program = *oracle_program_info.key;
+        market_info.serialize(&mut &mut market_info_account.data.borrow_mut()[..])?;
+        Ok(())
+    }
+    fn update_auction_defaults(
+        program_id: Pubkey,
+        account_info: &[AccountInfo],
+        extension: AuctionExtension,
+        min_auction_duration: u64,
+        max_auction_duration: u64,
+    ) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let admin_info = next_account_info(accounts)?;
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        let mut market_info = Self::load_market_info(&program_id, market_info_account)?;
+        if !admin_info.is_signer || *admin_info.key != market_info.owner_wallet_address {
+            return Err(MarketError::WrongOwner.into());
+        }
+        if max_auction_duration == 0 || min_auction_duration > max_auction_duration {
+            return Err(MarketError::InvalidInstruction.into());
+        }
+        fees::check_extension(&extension)?;
+        market_info.default_extension = extension;
+        market_info.min_auction_duration = min_auction_duration;
+        market_info.max_auction_duration = max_auction_duration;
+        market_info.serialize(&mut &mut market_info_account.data.borrow_mut()[..])?;
+        Ok(())
+    }
+    // only nfts from approved collections (or creators) can be listed
+    fn add_allowlist_entry(
+        program_id: Pubkey,
+        account_info: &[AccountInfo],
+        kind: AllowlistKind,
+        address: Pubkey,
+    ) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let admin_info = next_account_info(accounts)?;
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        let entry_account_info = next_account_info(accounts)?; // allowlist pda to be created
+        let sys_program_info = next_account_info(accounts)?;
+        let market_info = Self::load_market_info(&program_id, market_info_account)?;
+        if !admin_info.is_signer || *admin_info.key != market_info.owner_wallet_address {
+            return Err(MarketError::WrongOwner.into());
+        }
+        let (entry_pda, entry_nonce) =
+            Pubkey::find_program_address(&[b"allowlist", address.as_ref()], &program_id);
+        if *entry_account_info.key != entry_pda {
+            return Err(MarketError::PdaError.into());
+        }
+        // create_account fails if the address is already approved
+        let entry = AllowlistEntry {
+            is_initialized: true,
+            kind,
+            address,
+        };
+        let space = get_instance_packed_len(&entry)?;
+        invoke_signed(
+            &system_instruction::create_account(
+                admin_info.key,
+                entry_account_info.key,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                &program_id,
+            ),
+            &[
+                admin_info.clone(),
+                entry_account_info.clone(),
+                sys_program_info.clone(),
+            ],
+            &[&[&b"allowlist"[..], address.as_ref(), &[entry_nonce]]],
+        )?;
+        entry.serialize(&mut &mut entry_account_info.data.borrow_mut()[..])?;
+        Ok(())
+    }
+    // existing listings are unaffected, only new ones are refused
+    fn remove_allowlist_entry(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let admin_info = next_account_info(accounts)?;
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        let entry_account_info = next_account_info(accounts)?;
+        let market_info = Self::load_market_info(&program_id, market_info_account)?;
+        if !admin_info.is_signer || *admin_info.key != market_info.owner_wallet_address {
+            return Err(MarketError::WrongOwner.into());
+        }
+        Self::load_allowlist_entry(&program_id, entry_account_info)?;
+        Self::close_program_account(entry_account_info, admin_info)
+    }
+    // the nft's verified collection, or one of its verified creators, has to
+    // be on the allowlist
+    fn check_allowlisted(
+        program_id: &Pubkey,
+        metadata: &Metadata,
+        entry_account_info: &AccountInfo,
+    ) -> ProgramResult {
+        let entry = Self::load_allowlist_entry(program_id, entry_account_info)
+            .map_err(|_| MarketError::NotAllowlisted)?;
+        let approved = match entry.kind {
+            AllowlistKind::Collection => metadata
+                .collection
+                .iter()
+                .any(|collection| collection.verified && collection.key == entry.address),
+            AllowlistKind::Creator => metadata
+                .data
+                .creators
+                .iter()
+                .flatten()
+                .any(|creator| creator.verified && creator.address == entry.address),
+        };
+        if !approved {
+            return Err(MarketError::NotAllowlisted.into());
+        }
+        Ok(())
+    }
+    // has to be a live entry at its own pda, not just any program account
+    fn load_allowlist_entry(
+        program_id: &Pubkey,
+        entry_account_info: &AccountInfo,
+    ) -> Result<AllowlistEntry, ProgramError> {
+        if entry_account_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let entry: AllowlistEntry =
+            BorshDeserialize::try_from_slice(&entry_account_info.data.borrow())?;
+        let (entry_pda, _nonce) =
+            Pubkey::find_program_address(&[b"allowlist", entry.address.as_ref()], program_id);
+        if !entry.is_initialized || *entry_account_info.key != entry_pda {
+            return Err(MarketError::PdaError.into());
+        }
+        Ok(entry)
+    }
+    // `end` must be after `start` and the gap within the marketplace bounds
+    fn check_auction_duration(
+        market_info: &BlackMarketInfo,
+        start: u64,
+        end: u64,
+    ) -> ProgramResult {
+        let duration = end
+            .checked_sub(start)
+            .filter(|duration| *duration != 0)
+            .ok_or(MarketError::InvalidInstruction)?;
+        if duration < market_info.min_auction_duration {
+            return Err(MarketError::MinTimeLimit.into());
+        }
+        if duration > market_info.max_auction_duration {
+            return Err(MarketError::MaxTimeLimit.into());
+        }
+        Ok(())
+    }
+    fn check_fees(
+        auction_fee_basis_points: u16,
+        raffle_fee_basis_points: u16,
+        featuring_fee_basis_points: u16,
+    ) -> ProgramResult {
+        // a featured raffle pays both the raffle and the featuring cut
+        if auction_fee_basis_points > 10000
+            || raffle_fee_basis_points as u32 + featuring_fee_basis_points as u32 > 10000
+        {
+            return Err(MarketError::InvalidFee.into());
+        }
+        Ok(())
+    }
+    fn load_market_info(
+        program_id: &Pubkey,
+        market_info_account: &AccountInfo,
+    ) -> Result<BlackMarketInfo, ProgramError> {
+        let (market_info_pda, _nonce) =
+            Pubkey::find_program_address(&[b"black_market_info"], program_id);
+        if *market_info_account.key != market_info_pda {
+            return Err(MarketError::PdaError.into());
+        }
+        if market_info_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let market_info: BlackMarketInfo =
+            BorshDeserialize::try_from_slice(&market_info_account.data.borrow())?;
+        if !market_info.is_initialized {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        Ok(market_info)
+    }
+    // english auction, paid in native sol or any spl mint
+    #[allow(clippy::too_many_arguments)]
     fn auction_order(
         program_id: Pubkey,
         account_info: &[AccountInfo],
         minimum_price: u64,
         time: u64,
+        buy_now_price: u64,
+        reserve_commitment: [u8; 32],
+        extension: Option<AuctionExtension>,
+        increment: Option<BidIncrement>,
+        start_time: u64,
     ) -> ProgramResult {
         let accounts = &mut account_info.iter();
         let holder_info = next_account_info(accounts)?; //cat king wallet
@@ -94,56 +548,39 @@ impl Processor {
         let auction_order_account_info = next_account_info(accounts)?; // auction data account
         let token_program = next_account_info(accounts)?; // token program
         let metadata_account = next_account_info(accounts)?;
-        let token_type_info = next_account_info(accounts)?;
-        let metadata = Metadata::from_account_info(metadata_account)?;
-        let mut found = 0;
-        if *auction_order_account_info.owner != program_id {
-            return Err(ProgramError::IncorrectProgramId);
-        }
-        if metadata.collection.is_some() || metadata.data.creators.is_some() {
-            if let Some(collection) = metadata.collection {
-                if collection.verified == true {
-                    found += 1;
-                }
-            }
-            if let Some(creators) = metadata.data.creators {
-                if creators[0].verified == true {
-                    found += 1;
-                }
-            }
-        } else {
-            return Err(MarketError::InvalidInstruction.into());
-        }
-        if found == 0 {
-            return Err(MarketError::UnverifiedNFT.into());
-        }
-
-        let mut auction_order_struct: AuctionOrder =
-            BorshDeserialize::try_from_slice(&mut auction_order_account_info.data.borrow())?;
-        if auction_order_struct.is_initialized == true {
+        let token_type_info = next_account_info(accounts)?; // payment mint, wrapped sol for sol
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        let nft_mint_account_info = next_account_info(accounts)?;
+        let allowlist_entry_info = next_account_info(accounts)?; // approved collection or creator
+        let market_info = Self::load_market_info(&program_id, market_info_account)?;
+        let mut auction_order_struct =
+            Self::load_auction_order(&program_id, auction_order_account_info)?;
+        let metadata = Self::check_verified_metadata(
+            metadata_account,
+            token_account_info,
+            nft_mint_account_info,
+        )?;
+        Self::check_allowlisted(&program_id, &metadata, allowlist_entry_info)?;
+        if auction_order_struct.is_initialized {
             return Err(ProgramError::AccountAlreadyInitialized);
         }
         auction_order_struct.is_initialized = true;
         auction_order_struct.owner_wallet_address = *holder_info.key;
         auction_order_struct.token_account = *token_account_info.key;
-        if (time - Clock::get()?.unix_timestamp as u64) < 604800 {
-            auction_order_struct.time = time;
-        } else {
-            return Err(MarketError::MaxTimeLimit.into());
-        }
+        auction_order_struct.payment = Self::payment_mint(token_type_info.key);
+        let start_time = start_time.max(Clock::get()?.unix_timestamp as u64);
+        Self::check_auction_duration(&market_info, start_time, time)?;
+        auction_order_struct.time = time;
         auction_order_struct.bid = 0;
-        if SPLS::Account::unpack_unchecked(&mut token_account_info.data.borrow())?.amount != 1 {
+        if SPLS::Account::unpack_unchecked(&token_account_info.data.borrow())?.amount != 1 {
             return Err(ProgramError::InsufficientFunds);
         }
-        if Clock::get()?.unix_timestamp as u64 > time {
-            return Err(MarketError::InvalidInstruction.into());
-        }
-        if minimum_price as f64 / 1000000000.00 == 0.00 || holder_info.is_signer != true {
+        if minimum_price == 0 || !holder_info.is_signer {
             return Err(MarketError::MinPrice.into());
         }
-        let (pda, _nonce) =
-            Pubkey::find_program_address(&[b"seeds_for_pda"], &program_id);
-        if let Err(error) = invoke(
+        let seeds = Self::auction_pda_seeds(&auction_order_struct.payment);
+        let (pda, _nonce) = Pubkey::find_program_address(&[seeds], &program_id);
+        invoke(
             &SPLIX::set_authority(
                 token_program.key,
                 token_account_info.key,
@@ -157,140 +594,291 @@ impl Processor {
                 token_account_info.clone(),
                 holder_info.clone(),
             ],
-        ) {
-            return Err(error);
-        }
+        )?;
         auction_order_struct.minimum_price = minimum_price;
         auction_order_struct.total_bid_amount = 0;
-        auction_order_struct.token_type = *token_type_info.key;
+        if buy_now_price != 0 && buy_now_price <= minimum_price {
+            return Err(MarketError::MinPrice.into());
+        }
+        auction_order_struct.buy_now_price = buy_now_price;
+        auction_order_struct.reserve_commitment = reserve_commitment;
+        let extension = extension.unwrap_or(market_info.default_extension);
+        fees::check_extension(&extension)?;
+        auction_order_struct.extension = extension;
+        let increment = increment.unwrap_or_else(fees::default_increment);
+        fees::check_increment(&increment)?;
+        auction_order_struct.increment = increment;
+        auction_order_struct.start_time = start_time;
         auction_order_struct
             .serialize(&mut &mut auction_order_account_info.data.borrow_mut()[..])?;
         Ok(())
     }
-    fn place_bid(program_id: Pubkey, account_info: &[AccountInfo], bid: u64) -> ProgramResult {
+    // sized for an spl mint, a sol auction leaves the tail of the account unused
+    fn load_auction_order(
+        program_id: &Pubkey,
+        auction_order_account_info: &AccountInfo,
+    ) -> Result<AuctionOrder, ProgramError> {
+        if auction_order_account_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Ok(try_from_slice_unchecked(
+            &auction_order_account_info.data.borrow(),
+        )?)
+    }
+    // wrapped sol as the payment mint means native sol
+    fn payment_mint(mint: &Pubkey) -> PaymentMint {
+        if *mint == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap() {
+            PaymentMint::Native
+        } else {
+            PaymentMint::Spl(*mint)
+        }
+    }
+    // sol auctions escrow lamports and the nft under their own pda
+    fn auction_pda_seeds(payment: &PaymentMint) -> &'static [u8] {
+        match payment {
+            PaymentMint::Native => b"seeds_for_pda$0!",
+            PaymentMint::Spl(_) => b"seeds_for_pda",
+        }
+    }
+    // the escrow is the pda itself for sol, a pda owned token account otherwise
+    fn check_escrow_account(
+        payment: &PaymentMint,
+        escrow_account_info: &AccountInfo,
+        pda: &Pubkey,
+    ) -> ProgramResult {
+        match payment {
+            PaymentMint::Native => {
+                if escrow_account_info.key != pda {
+                    return Err(MarketError::PdaError.into());
+                }
+            }
+            PaymentMint::Spl(_) => Self::check_payment_account(payment, escrow_account_info, pda)?,
+        }
+        Ok(())
+    }
+    // a party pays and is paid through their wallet for sol, or through
+    // their token account of the payment mint for spl
+    fn check_payment_account(
+        payment: &PaymentMint,
+        payment_account_info: &AccountInfo,
+        wallet: &Pubkey,
+    ) -> ProgramResult {
+        match payment {
+            PaymentMint::Native => {
+                if payment_account_info.key != wallet {
+                    return Err(MarketError::WrongOwner.into());
+                }
+            }
+            PaymentMint::Spl(mint) => {
+                let token_account =
+                    SPLS::Account::unpack_unchecked(&payment_account_info.data.borrow())?;
+                if token_account.owner != *wallet {
+                    return Err(MarketError::WrongOwner.into());
+                }
+                if token_account.mint != *mint {
+                    return Err(MarketError::ValueMisMatch.into());
+                }
+            }
+        }
+        Ok(())
+    }
+    fn check_treasury_account(
+        payment: &PaymentMint,
+        treasury_account_info: &AccountInfo,
+        market_info: &BlackMarketInfo,
+    ) -> ProgramResult {
+        let treasury = match payment {
+            PaymentMint::Native => &market_info.treasury_wallet_address,
+            PaymentMint::Spl(_) => &market_info.spl_treasury_wallet_address,
+        };
+        Self::check_payment_account(payment, treasury_account_info, treasury)
+    }
+    // moves `amount` of the auction's payment, signer seeds are needed when
+    // it leaves the escrow
+    fn transfer_payment<'a>(
+        payment: &PaymentMint,
+        payment_program: &AccountInfo<'a>,
+        from: &AccountInfo<'a>,
+        to: &AccountInfo<'a>,
+        authority: &AccountInfo<'a>,
+        amount: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        if amount == 0 {
+            return Ok(());
+        }
+        let instruction = match payment {
+            PaymentMint::Native => transfer(from.key, to.key, amount),
+            PaymentMint::Spl(_) => SPLIX::transfer(
+                payment_program.key,
+                from.key,
+                to.key,
+                authority.key,
+                &[],
+                amount,
+            )?,
+        };
+        invoke_signed(
+            &instruction,
+            &[
+                from.clone(),
+                to.clone(),
+                authority.clone(),
+                payment_program.clone(),
+            ],
+            signer_seeds,
+        )
+    }
+    // soft close, pushes the end back when a bid lands inside the window
+    fn extend_auction(
+        time: &mut u64,
+        extended_by: &mut u64,
+        extension: &AuctionExtension,
+    ) -> ProgramResult {
+        let now = Clock::get()?.unix_timestamp as u64;
+        if now >= *time || *time - now > extension.window {
+            return Ok(());
+        }
+        let mut length = extension.length;
+        if extension.max_total != 0 {
+            length = length.min(extension.max_total.saturating_sub(*extended_by));
+        }
+        *time = time.checked_add(length).ok_or(MarketError::MathOverflow)?;
+        *extended_by = extended_by
+            .checked_add(length)
+            .ok_or(MarketError::MathOverflow)?;
+        Ok(())
+    }
+    fn place_bid(
+        program_id: Pubkey,
+        account_info: &[AccountInfo],
+        bid: u64,
+        max_bid: u64,
+    ) -> ProgramResult {
         let accounts = &mut account_info.iter();
         let bidder_account_info = next_account_info(accounts)?; // bidder wallet
-        let biddder_spl_token_account_info = next_account_info(accounts)?; // spl to be transferred from
-        let spl_mint_account_info = next_account_info(accounts)?;
+        let bidder_payment_account_info = next_account_info(accounts)?; // pays the bid
         let auction_order_account_info = next_account_info(accounts)?; // auction data to be updated account
-        let previous_bidder = next_account_info(accounts)?; // previous bidder info
-        let previous_bidder_spl_token_account_info = next_account_info(accounts)?; //
-        let token_program = next_account_info(accounts)?;
-        let pda_spl_token_account_info = next_account_info(accounts)?; // would be created once
+        let previous_bidder_payment_account_info = next_account_info(accounts)?; // refunded when outbid
+        let escrow_account_info = next_account_info(accounts)?; // holds the top bid
         let pda_account_info = next_account_info(accounts)?;
-        if *auction_order_account_info.owner != program_id {
-            return Err(ProgramError::IncorrectProgramId);
+        let payment_program = next_account_info(accounts)?; // system program for sol, token program for spl
+        let mut auction_order_struct =
+            Self::load_auction_order(&program_id, auction_order_account_info)?;
+        let payment = auction_order_struct.payment;
+        let seeds = Self::auction_pda_seeds(&payment);
+        let (pda, _nonce) = Pubkey::find_program_address(&[seeds], &program_id);
+        if !auction_order_struct.is_initialized {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !bidder_account_info.is_signer {
+            return Err(MarketError::WrongOwner.into());
         }
-        let mut auction_order_struct: AuctionOrder =
-            BorshDeserialize::try_from_slice(&mut auction_order_account_info.data.borrow())?;
-        let (pda, _nonce) =
-            Pubkey::find_program_address(&[b"seeds_for_pda"], &program_id);
-        //initial bid
         if auction_order_struct.owner_wallet_address == *bidder_account_info.key {
             return Err(MarketError::OwnerCannotBid.into());
         }
-        if *previous_bidder_spl_token_account_info.key
-            != auction_order_struct.bidder_spl_token_account
-        {
-            return Err(MarketError::ValueMisMatch.into());
+        if (Clock::get()?.unix_timestamp as u64) < auction_order_struct.start_time {
+            return Err(MarketError::AuctionNotStarted.into());
         }
         if &pda != pda_account_info.key {
             return Err(MarketError::PdaError.into());
         }
-        if SPLS::Account::unpack_unchecked(&mut pda_spl_token_account_info.data.borrow())?.owner
-            != *pda_account_info.key
-        {
-            return Err(MarketError::WrongOwner.into());
-        }
-        let bid_increment = ((auction_order_struct.total_bid_amount as f64 * 5.00) / 100.00) as u64;
-        if bid < bid_increment + auction_order_struct.bid {
+        Self::check_escrow_account(&payment, escrow_account_info, &pda)?;
+        Self::check_payment_account(
+            &payment,
+            bidder_payment_account_info,
+            bidder_account_info.key,
+        )?;
+        let min_bid =
+            fees::min_next_bid_for(&auction_order_struct.increment, auction_order_struct.bid)?;
+        if bid < min_bid {
             return Err(MarketError::BidMustBeGreater.into());
         }
-        if auction_order_struct.time - Clock::get()?.unix_timestamp as u64 <= 120 {
-            auction_order_struct.time += 120;
-        }
+        // the whole ceiling is escrowed, the program bids for them up to it
+        let ceiling = bid.max(max_bid);
+        Self::extend_auction(
+            &mut auction_order_struct.time,
+            &mut auction_order_struct.extended_by,
+            &auction_order_struct.extension,
+        )?;
         if bid > auction_order_struct.minimum_price
             && auction_order_struct.bid == 0
             && auction_order_struct.time > Clock::get()?.unix_timestamp as u64
         {
-            if let Err(error) = invoke(
-                &SPLIX::transfer(
-                    token_program.key,
-                    biddder_spl_token_account_info.key,
-                    pda_spl_token_account_info.key,
-                    bidder_account_info.key,
-                    &[bidder_account_info.key],
-                    bid,
-                )?,
-                &[
-                    biddder_spl_token_account_info.clone(),
-                    spl_mint_account_info.clone(),
-                    pda_spl_token_account_info.clone(),
-                    bidder_account_info.clone(),
-                ],
-            ) {
-                return Err(error);
-            }
+            Self::transfer_payment(
+                &payment,
+                payment_program,
+                bidder_payment_account_info,
+                escrow_account_info,
+                bidder_account_info,
+                ceiling,
+                &[],
+            )?;
             auction_order_struct.bidder_wallet_address = *bidder_account_info.key;
-            auction_order_struct.bidder_spl_token_account = *biddder_spl_token_account_info.key;
+            auction_order_struct.bidder_payment_account = *bidder_payment_account_info.key;
             auction_order_struct.bid = bid;
-            auction_order_struct.total_bid_amount = bid;
+            auction_order_struct.proxy_max = ceiling;
         } else if bid > auction_order_struct.bid //bigger bid
             && auction_order_struct.time >Clock::get()?.unix_timestamp as u64
             && auction_order_struct.minimum_price < bid
         {
-            //setting new bid
-            if let Err(error) = invoke(
-                &SPLIX::transfer(
-                    token_program.key,
-                    biddder_spl_token_account_info.key,
-                    pda_spl_token_account_info.key,
-                    bidder_account_info.key,
-                    &[bidder_account_info.key],
+            if auction_order_struct.bidder_wallet_address == *bidder_account_info.key {
+                // the leader raising their own ceiling, the price stays put
+                if *bidder_payment_account_info.key != auction_order_struct.bidder_payment_account {
+                    return Err(MarketError::ValueMisMatch.into());
+                }
+                if ceiling <= auction_order_struct.proxy_max {
+                    return Err(MarketError::BidMustBeGreater.into());
+                }
+                Self::transfer_payment(
+                    &payment,
+                    payment_program,
+                    bidder_payment_account_info,
+                    escrow_account_info,
+                    bidder_account_info,
+                    ceiling - auction_order_struct.proxy_max,
+                    &[],
+                )?;
+                auction_order_struct.proxy_max = ceiling;
+            } else {
+                let (price, takes_lead) = fees::proxy_bid_price(
+                    &auction_order_struct.increment,
+                    auction_order_struct.proxy_max,
                     bid,
-                )?,
-                &[
-                    biddder_spl_token_account_info.clone(),
-                    spl_mint_account_info.clone(),
-                    pda_spl_token_account_info.clone(),
-                    bidder_account_info.clone(),
-                ],
-            ) {
-                return Err(error);
-            }
-            if *previous_bidder.key != auction_order_struct.bidder_wallet_address
-                || *previous_bidder.key
-                    != SPLS::Account::unpack_unchecked(
-                        &mut previous_bidder_spl_token_account_info.data.borrow(),
-                    )?
-                    .owner
-            {
-                return Err(ProgramError::IllegalOwner);
-            }
-            //refunding previous bidder
-            if let Err(error) = invoke_signed(
-                &SPLIX::transfer(
-                    token_program.key,
-                    pda_spl_token_account_info.key,
-                    previous_bidder_spl_token_account_info.key,
-                    &pda,
-                    &[&pda],
-                    auction_order_struct.bid,
-                )?,
-                &[
-                    pda_spl_token_account_info.clone(),
-                    spl_mint_account_info.clone(),
-                    previous_bidder_spl_token_account_info.clone(),
-                    pda_account_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-            ) {
-                return Err(error);
+                    ceiling,
+                )?;
+                if takes_lead {
+                    if *previous_bidder_payment_account_info.key
+                        != auction_order_struct.bidder_payment_account
+                    {
+                        return Err(ProgramError::IllegalOwner);
+                    }
+                    //setting new bid
+                    Self::transfer_payment(
+                        &payment,
+                        payment_program,
+                        bidder_payment_account_info,
+                        escrow_account_info,
+                        bidder_account_info,
+                        ceiling,
+                        &[],
+                    )?;
+                    //refunding previous bidder
+                    Self::transfer_payment(
+                        &payment,
+                        payment_program,
+                        escrow_account_info,
+                        previous_bidder_payment_account_info,
+                        pda_account_info,
+                        auction_order_struct.proxy_max,
+                        &[&[seeds, &[_nonce]]],
+                    )?;
+                    auction_order_struct.bidder_wallet_address = *bidder_account_info.key;
+                    auction_order_struct.bidder_payment_account = *bidder_payment_account_info.key;
+                    auction_order_struct.proxy_max = ceiling;
+                }
+                auction_order_struct.bid = price;
             }
-            auction_order_struct.bidder_wallet_address = *bidder_account_info.key;
-            auction_order_struct.bidder_spl_token_account = *biddder_spl_token_account_info.key;
-            auction_order_struct.bid = bid;
-            auction_order_struct.total_bid_amount = auction_order_struct.total_bid_amount + bid;
         } else {
             return Err(MarketError::BidMustBeGreater.into());
         }
@@ -301,208 +889,683 @@ impl Processor {
     }
     //admin 100
     fn complete_auction_order(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
+        Self::settle_auction_order(program_id, account_info, true, false)
+    }
+    //admin 2
+    fn complete_auction_order_any_time(
+        program_id: Pubkey,
+        account_info: &[AccountInfo],
+    ) -> ProgramResult {
+        Self::settle_auction_order(program_id, account_info, false, true)
+    }
+    fn complete_auction_order_user(
+        program_id: Pubkey,
+        account_info: &[AccountInfo],
+    ) -> ProgramResult {
+        Self::settle_auction_order(program_id, account_info, false, false)
+    }
+    // hands the nft to the top bidder and pays the seller. `fee_free` is for
+    // the marketplace's own listings, `accept_early` lets the seller take
+    // the current bid before the end, otherwise the winner claims after it
+    fn settle_auction_order(
+        program_id: Pubkey,
+        account_info: &[AccountInfo],
+        fee_free: bool,
+        accept_early: bool,
+    ) -> ProgramResult {
         let accounts = &mut account_info.iter();
-        let king = next_account_info(accounts)?; //holder info
-        let king_spl_token_account = next_account_info(accounts)?; //can be a static account value for now to transfer the bid amount
-        let bidder_info = next_account_info(accounts)?; //get from sellerOrder Account in web3 claiming k liyey
-        let auction_order_account_info = next_account_info(accounts)?; //fetch data and matach with web3
-        let auction_nft_token_account_info = next_account_info(accounts)?; //from auciton order Account in web3
-        let pda_account_info = next_account_info(accounts)?; //which holder the authority for NFT on Auction
-        let auction_nft_new_token_account = next_account_info(accounts)?; // new token account of user to send nft to
-        let auction_nft_mint = next_account_info(accounts)?; // auction TOken mint account for transfer function
+        let holder_info = next_account_info(accounts)?; // seller wallet
+        let holder_payment_account_info = next_account_info(accounts)?; // receives the bid
+        let bidder_info = next_account_info(accounts)?; // top bidder wallet
+        let bidder_payment_account_info = next_account_info(accounts)?; // gets back any unused proxy ceiling
+        let auction_order_account_info = next_account_info(accounts)?;
+        let auction_nft_token_account_info = next_account_info(accounts)?; // escrowed nft
+        let auction_nft_mint = next_account_info(accounts)?;
+        let auction_nft_new_token_account = next_account_info(accounts)?; // winner's token account for the nft
+        let pda_account_info = next_account_info(accounts)?;
+        let escrow_account_info = next_account_info(accounts)?; // holds the top bid
         let token_program = next_account_info(accounts)?;
-        let pda_spl_token_account_info = next_account_info(accounts)?; // to transfer to cat king spl token acount
-        let spl_mint_account_info = next_account_info(accounts)?; // zoin mint static value
-        let auction_order_struct: AuctionOrder =
-            BorshDeserialize::try_from_slice(&mut auction_order_account_info.data.borrow())?;
-        if *auction_order_account_info.owner != program_id {
-            return Err(ProgramError::IncorrectProgramId);
+        let payment_program = next_account_info(accounts)?; // system program for sol, token program for spl
+        let treasury_payment_account_info = next_account_info(accounts)?;
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        let metadata_account = next_account_info(accounts)?; // nft metadata, creator payment accounts follow
+        let market_info = Self::load_market_info(&program_id, market_info_account)?;
+        let auction_order_struct =
+            Self::load_auction_order(&program_id, auction_order_account_info)?;
+        let payment = auction_order_struct.payment;
+        // fee free settlement is reserved for the marketplace's own listings
+        if fee_free && auction_order_struct.owner_wallet_address != market_info.owner_wallet_address
+        {
+            return Err(MarketError::WrongOwner.into());
         }
-        if bidder_info.is_signer != true
-            || *king.key != auction_order_struct.owner_wallet_address
+        let signer_info = if accept_early {
+            holder_info
+        } else {
+            bidder_info
+        };
+        if !signer_info.is_signer
+            || *holder_info.key != auction_order_struct.owner_wallet_address
             || *auction_nft_token_account_info.key != auction_order_struct.token_account
             || *bidder_info.key != auction_order_struct.bidder_wallet_address
+            || *bidder_payment_account_info.key != auction_order_struct.bidder_payment_account
+            || auction_order_struct.bid == 0
         {
             return Err(MarketError::ValueMisMatch.into());
         }
-        if SPLS::Account::unpack_unchecked(&mut auction_nft_new_token_account.data.borrow())?.owner
+        if SPLS::Account::unpack_unchecked(&auction_nft_new_token_account.data.borrow())?.owner
             != *bidder_info.key
         {
             return Err(MarketError::WrongOwner.into());
         }
-        let (pda, _nonce) =
-            Pubkey::find_program_address(&[b"seeds_for_pda"], &program_id);
+        let seeds = Self::auction_pda_seeds(&payment);
+        let (pda, _nonce) = Pubkey::find_program_address(&[seeds], &program_id);
         if *pda_account_info.key != pda {
             return Err(MarketError::PdaError.into());
         }
-        if SPLS::Account::unpack_unchecked(&mut king_spl_token_account.data.borrow())?.owner
-            != auction_order_struct.owner_wallet_address
+        Self::check_escrow_account(&payment, escrow_account_info, &pda)?;
+        Self::check_payment_account(&payment, holder_payment_account_info, holder_info.key)?;
+        Self::check_treasury_account(&payment, treasury_payment_account_info, &market_info)?;
+        if !accept_early {
+            Self::check_reserve_met(
+                &auction_order_struct.reserve_commitment,
+                auction_order_struct.is_reserve_revealed,
+                auction_order_struct.reserve_price,
+                auction_order_struct.bid,
+                auction_order_struct.time,
+            )?;
+            if Clock::get()?.unix_timestamp as u64 <= auction_order_struct.time {
+                return Err(MarketError::AuctionNotEnded.into());
+            }
+        }
+        let fee_basis_points = if fee_free {
+            0
+        } else {
+            market_info.auction_fee_basis_points
+        };
+        let sale_accounts = &[
+            holder_info.clone(),
+            holder_payment_account_info.clone(),
+            treasury_payment_account_info.clone(),
+            auction_nft_token_account_info.clone(),
+            auction_nft_mint.clone(),
+            auction_nft_new_token_account.clone(),
+            pda_account_info.clone(),
+            escrow_account_info.clone(),
+            token_program.clone(),
+            payment_program.clone(),
+            metadata_account.clone(),
+        ];
+        Self::settle_auction_sale(
+            &program_id,
+            &payment,
+            sale_accounts,
+            accounts,
+            auction_order_struct.bid,
+            fee_basis_points,
+        )?;
+        Self::transfer_payment(
+            &payment,
+            payment_program,
+            escrow_account_info,
+            bidder_payment_account_info,
+            pda_account_info,
+            auction_order_struct
+                .proxy_max
+                .saturating_sub(auction_order_struct.bid),
+            &[&[seeds, &[_nonce]]],
+        )?;
+        //closing the auction order account
+        Self::close_program_account(auction_order_account_info, holder_info)?;
+        Ok(())
+    }
+    // the seller shows the reserve once bidding is over, the top bidder can
+    // then settle if it was met, or the seller cancels and refunds them
+    fn reveal_reserve(
+        program_id: Pubkey,
+        account_info: &[AccountInfo],
+        reserve: u64,
+        salt: [u8; 32],
+    ) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let holder_info = next_account_info(accounts)?;
+        let auction_order_account_info = next_account_info(accounts)?;
+        let mut auction_order_struct =
+            Self::load_auction_order(&program_id, auction_order_account_info)?;
+        if !holder_info.is_signer || *holder_info.key != auction_order_struct.owner_wallet_address
         {
             return Err(MarketError::WrongOwner.into());
         }
-
-        //transferring spl to cat king
-        if Clock::get()?.unix_timestamp as u64 > auction_order_struct.time {
-            if let Err(error) = invoke_signed(
-                &SPLIX::transfer(
-                    token_program.key,
-                    pda_spl_token_account_info.key,
-                    king_spl_token_account.key,
-                    &pda,
-                    &[&pda],
-                    auction_order_struct.bid,
-                )?,
-                &[
-                    pda_spl_token_account_info.clone(),
-                    spl_mint_account_info.clone(),
-                    king_spl_token_account.clone(),
-                    pda_account_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-            // transfering NFT to bidder when they claim
-            if let Err(error) = invoke_signed(
-                &SPLIX::transfer_checked(
-                    token_program.key,
-                    auction_nft_token_account_info.key,
-                    auction_nft_mint.key,
-                    auction_nft_new_token_account.key,
-                    &pda,
-                    &[&pda],
-                    1,
-                    0,
-                )?,
-                &[
-                    auction_nft_token_account_info.clone(),
-                    auction_nft_mint.clone(),
-                    auction_nft_new_token_account.clone(),
-                    pda_account_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-            ) {
-                return Err(error);
+        if Clock::get()?.unix_timestamp as u64 <= auction_order_struct.time {
+            return Err(MarketError::AuctionNotEnded.into());
+        }
+        Self::check_reserve_reveal(&auction_order_struct.reserve_commitment, reserve, &salt)?;
+        auction_order_struct.is_reserve_revealed = true;
+        auction_order_struct.reserve_price = reserve;
+        // a proxy ceiling at or over the reserve bids straight up to it
+        if auction_order_struct.bid < reserve && auction_order_struct.proxy_max >= reserve {
+            auction_order_struct.bid = reserve;
+        }
+        auction_order_struct
+            .serialize(&mut &mut auction_order_account_info.data.borrow_mut()[..])?;
+        Ok(())
+    }
+    fn check_reserve_reveal(
+        reserve_commitment: &[u8; 32],
+        reserve: u64,
+        salt: &[u8; 32],
+    ) -> ProgramResult {
+        if *reserve_commitment == [0; 32] {
+            return Err(MarketError::InvalidInstruction.into());
+        }
+        if hashv(&[&reserve.to_le_bytes(), salt]).to_bytes() != *reserve_commitment {
+            return Err(MarketError::SeedMismatch.into());
+        }
+        Ok(())
+    }
+    // a bidder can settle once the reserve is shown to be met, or once the
+    // seller has let RESERVE_REVEAL_PERIOD pass without revealing it
+    fn check_reserve_met(
+        reserve_commitment: &[u8; 32],
+        is_reserve_revealed: bool,
+        reserve_price: u64,
+        bid: u64,
+        end_time: u64,
+    ) -> ProgramResult {
+        if *reserve_commitment == [0; 32] {
+            return Ok(());
+        }
+        if is_reserve_revealed {
+            if bid < reserve_price {
+                return Err(MarketError::ReserveNotMet.into());
             }
-            if let Err(error) = invoke_signed(
-                &SPLIX::close_account(
+        } else if Clock::get()?.unix_timestamp as u64
+            <= end_time.saturating_add(RESERVE_REVEAL_PERIOD)
+        {
+            return Err(MarketError::ReserveNotMet.into());
+        }
+        Ok(())
+    }
+    // ends the auction at the buy-now price, the top bidder is refunded
+    fn buy_now(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let buyer_info = next_account_info(accounts)?; // buyer wallet
+        let buyer_payment_account_info = next_account_info(accounts)?; // pays the buy-now price
+        let holder_info = next_account_info(accounts)?; // seller wallet
+        let holder_payment_account_info = next_account_info(accounts)?;
+        let auction_order_account_info = next_account_info(accounts)?;
+        let auction_nft_token_account_info = next_account_info(accounts)?; // escrowed nft
+        let auction_nft_mint = next_account_info(accounts)?;
+        let auction_nft_new_token_account = next_account_info(accounts)?; // buyer's token account for the nft
+        let pda_account_info = next_account_info(accounts)?;
+        let escrow_account_info = next_account_info(accounts)?; // holds the top bid
+        let previous_bidder_payment_account_info = next_account_info(accounts)?;
+        let token_program = next_account_info(accounts)?;
+        let payment_program = next_account_info(accounts)?; // system program for sol, token program for spl
+        let treasury_payment_account_info = next_account_info(accounts)?;
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        let metadata_account = next_account_info(accounts)?; // nft metadata, creator payment accounts follow
+        let market_info = Self::load_market_info(&program_id, market_info_account)?;
+        let auction_order_struct =
+            Self::load_auction_order(&program_id, auction_order_account_info)?;
+        let payment = auction_order_struct.payment;
+        if *holder_info.key != auction_order_struct.owner_wallet_address
+            || *auction_nft_token_account_info.key != auction_order_struct.token_account
+            || *previous_bidder_payment_account_info.key
+                != auction_order_struct.bidder_payment_account
+        {
+            return Err(MarketError::ValueMisMatch.into());
+        }
+        if !buyer_info.is_signer {
+            return Err(MarketError::WrongOwner.into());
+        }
+        if *buyer_info.key == auction_order_struct.owner_wallet_address {
+            return Err(MarketError::OwnerCannotBid.into());
+        }
+        if (Clock::get()?.unix_timestamp as u64) < auction_order_struct.start_time {
+            return Err(MarketError::AuctionNotStarted.into());
+        }
+        if Clock::get()?.unix_timestamp as u64 > auction_order_struct.time {
+            return Err(MarketError::BiddingClosed.into());
+        }
+        // bidding past the buy-now price takes the option away
+        if auction_order_struct.buy_now_price == 0
+            || auction_order_struct.bid >= auction_order_struct.buy_now_price
+        {
+            return Err(MarketError::InvalidInstruction.into());
+        }
+        if SPLS::Account::unpack_unchecked(&auction_nft_new_token_account.data.borrow())?.owner
+            != *buyer_info.key
+        {
+            return Err(MarketError::WrongOwner.into());
+        }
+        let seeds = Self::auction_pda_seeds(&payment);
+        let (pda, _nonce) = Pubkey::find_program_address(&[seeds], &program_id);
+        if *pda_account_info.key != pda {
+            return Err(MarketError::PdaError.into());
+        }
+        Self::check_escrow_account(&payment, escrow_account_info, &pda)?;
+        Self::check_payment_account(&payment, buyer_payment_account_info, buyer_info.key)?;
+        Self::check_payment_account(&payment, holder_payment_account_info, holder_info.key)?;
+        Self::check_treasury_account(&payment, treasury_payment_account_info, &market_info)?;
+        Self::transfer_payment(
+            &payment,
+            payment_program,
+            buyer_payment_account_info,
+            escrow_account_info,
+            buyer_info,
+            auction_order_struct.buy_now_price,
+            &[],
+        )?;
+        if auction_order_struct.bid != 0 {
+            //refunding previous bidder
+            Self::transfer_payment(
+                &payment,
+                payment_program,
+                escrow_account_info,
+                previous_bidder_payment_account_info,
+                pda_account_info,
+                auction_order_struct.proxy_max,
+                &[&[seeds, &[_nonce]]],
+            )?;
+        }
+        let sale_accounts = &[
+            holder_info.clone(),
+            holder_payment_account_info.clone(),
+            treasury_payment_account_info.clone(),
+            auction_nft_token_account_info.clone(),
+            auction_nft_mint.clone(),
+            auction_nft_new_token_account.clone(),
+            pda_account_info.clone(),
+            escrow_account_info.clone(),
+            token_program.clone(),
+            payment_program.clone(),
+            metadata_account.clone(),
+        ];
+        Self::settle_auction_sale(
+            &program_id,
+            &payment,
+            sale_accounts,
+            accounts,
+            auction_order_struct.buy_now_price,
+            market_info.auction_fee_basis_points,
+        )?;
+        //closing the auction order account
+        Self::close_program_account(auction_order_account_info, holder_info)?;
+        Ok(())
+    }
+    // pays the creators, seller and treasury out of the escrow and hands the
+    // nft to the buyer, used by every auction settlement
+    fn settle_auction_sale<'a>(
+        program_id: &Pubkey,
+        payment: &PaymentMint,
+        account_info: &[AccountInfo<'a>],
+        creator_accounts: &mut std::slice::Iter<AccountInfo<'a>>,
+        price: u64,
+        fee_basis_points: u16,
+    ) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let holder_info = next_account_info(accounts)?; // gets the nft account's rent back
+        let holder_payment_account_info = next_account_info(accounts)?;
+        let treasury_payment_account_info = next_account_info(accounts)?;
+        let sell_token_account_info = next_account_info(accounts)?;
+        let sell_mint_account_info = next_account_info(accounts)?;
+        let sell_token_new_account_info = next_account_info(accounts)?;
+        let pda_account_info = next_account_info(accounts)?;
+        let escrow_account_info = next_account_info(accounts)?;
+        let token_program = next_account_info(accounts)?;
+        let payment_program = next_account_info(accounts)?;
+        let metadata_account = next_account_info(accounts)?;
+        let seeds = Self::auction_pda_seeds(payment);
+        let (pda, _nonce) = Pubkey::find_program_address(&[seeds], program_id);
+        if SPLS::Account::unpack_unchecked(&sell_token_account_info.data.borrow())?.mint
+            != *sell_mint_account_info.key
+        {
+            return Err(MarketError::ValueMisMatch.into());
+        }
+        let metadata = Self::load_nft_metadata(metadata_account, sell_mint_account_info.key)?;
+        let (seller_amount, fee_amount) = fees::split_amount(price, fee_basis_points)?;
+        let royalty =
+            fees::royalty_amount(price, fee_amount, metadata.data.seller_fee_basis_points)?;
+        let royalty_paid = Self::pay_creator_royalties(
+            payment,
+            payment_program,
+            escrow_account_info,
+            pda_account_info,
+            creator_accounts,
+            &metadata,
+            royalty,
+            &[&[seeds, &[_nonce]]],
+        )?;
+        let seller_amount = seller_amount
+            .checked_sub(royalty_paid)
+            .ok_or(MarketError::MathOverflow)?;
+        Self::transfer_payment(
+            payment,
+            payment_program,
+            escrow_account_info,
+            holder_payment_account_info,
+            pda_account_info,
+            seller_amount,
+            &[&[seeds, &[_nonce]]],
+        )?;
+        Self::transfer_payment(
+            payment,
+            payment_program,
+            escrow_account_info,
+            treasury_payment_account_info,
+            pda_account_info,
+            fee_amount,
+            &[&[seeds, &[_nonce]]],
+        )?;
+        invoke_signed(
+            &SPLIX::transfer_checked(
+                token_program.key,
+                sell_token_account_info.key,
+                sell_mint_account_info.key,
+                sell_token_new_account_info.key,
+                &pda,
+                &[&pda],
+                1,
+                0,
+            )?,
+            &[
+                sell_token_account_info.clone(),
+                sell_mint_account_info.clone(),
+                sell_token_new_account_info.clone(),
+                pda_account_info.clone(),
+            ],
+            &[&[seeds, &[_nonce]]],
+        )?;
+        invoke_signed(
+            &SPLIX::close_account(
+                token_program.key,
+                sell_token_account_info.key,
+                holder_info.key,
+                &pda,
+                &[&pda],
+            )?,
+            &[
+                sell_token_account_info.clone(),
+                holder_info.clone(),
+                pda_account_info.clone(),
+            ],
+            &[&[seeds, &[_nonce]]],
+        )?;
+        Ok(())
+    }
+    fn cancel_auction(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let payer_info = next_account_info(accounts)?;
+        let token_account_info = next_account_info(accounts)?;
+        let auction_order_account_info = next_account_info(accounts)?;
+        let token_program = next_account_info(accounts)?;
+        let pda_account_info = next_account_info(accounts)?;
+        let escrow_account_info = next_account_info(accounts)?; // holds the top bid
+        let previous_bidder_payment_account_info = next_account_info(accounts)?;
+        let payment_program = next_account_info(accounts)?; // system program for sol, token program for spl
+        let auction_order_struct =
+            Self::load_auction_order(&program_id, auction_order_account_info)?;
+        let payment = auction_order_struct.payment;
+        if !payer_info.is_signer
+            || *payer_info.key != auction_order_struct.owner_wallet_address
+            || *token_account_info.key != auction_order_struct.token_account
+            || *previous_bidder_payment_account_info.key
+                != auction_order_struct.bidder_payment_account
+        {
+            return Err(ProgramError::IllegalOwner);
+        }
+        let seeds = Self::auction_pda_seeds(&payment);
+        let (pda, _nonce) = Pubkey::find_program_address(&[seeds], &program_id);
+        if *pda_account_info.key != pda {
+            return Err(MarketError::PdaError.into());
+        }
+        Self::check_escrow_account(&payment, escrow_account_info, &pda)?;
+        // with a reserve, an ended auction can only be declined below it
+        if auction_order_struct.bid != 0
+            && auction_order_struct.reserve_commitment != [0; 32]
+            && (!auction_order_struct.is_reserve_revealed
+                || auction_order_struct.bid >= auction_order_struct.reserve_price)
+        {
+            return Err(MarketError::CannotCancel.into());
+        }
+        // before the start nobody can have bid, so the seller can pull the listing
+        let now = Clock::get()?.unix_timestamp as u64;
+        if now > auction_order_struct.time || now < auction_order_struct.start_time {
+            invoke_signed(
+                &SPLIX::set_authority(
                     token_program.key,
-                    auction_nft_token_account_info.key,
-                    king.key,
+                    token_account_info.key,
+                    Some(payer_info.key),
+                    SPLIX::AuthorityType::AccountOwner,
                     &pda,
                     &[&pda],
                 )?,
                 &[
-                    auction_nft_token_account_info.clone(),
-                    king.clone(),
+                    token_program.clone(),
+                    token_account_info.clone(),
                     pda_account_info.clone(),
                 ],
-                &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-            //closing the auction order account
-            **king.try_borrow_mut_lamports()? = king
-                .lamports()
-                .checked_add(auction_order_account_info.lamports())
-                .ok_or(ProgramError::InsufficientFunds)?;
-            **auction_order_account_info.try_borrow_mut_lamports()? = 0;
-            *auction_order_account_info.try_borrow_mut_data()? = &mut [];
+                &[&[seeds, &[_nonce]]],
+            )?;
+            if auction_order_struct.bid != 0 {
+                Self::transfer_payment(
+                    &payment,
+                    payment_program,
+                    escrow_account_info,
+                    previous_bidder_payment_account_info,
+                    pda_account_info,
+                    auction_order_struct.proxy_max,
+                    &[&[seeds, &[_nonce]]],
+                )?;
+            }
+            Self::close_program_account(auction_order_account_info, payer_info)?;
         } else {
-            return Err(MarketError::AuctionNotEnded.into());
+            return Err(MarketError::CannotCancel.into());
         }
         Ok(())
     }
-    //admin 2
-    fn complete_auction_order_any_time(
+    // dutch auction, the price falls over time and the first buyer takes the nft
+    fn dutch_auction_order(
         program_id: Pubkey,
         account_info: &[AccountInfo],
+        start_price: u64,
+        floor_price: u64,
+        end_time: u64,
+        decay: DecaySchedule,
+        step_interval: u64,
     ) -> ProgramResult {
         let accounts = &mut account_info.iter();
-        let holder_info = next_account_info(accounts)?; //holder info
-        let holder_spl_token_account = next_account_info(accounts)?; //can be a static account value for now to transfer the bid amount
-        let bidder_info = next_account_info(accounts)?; //get from sellerOrder Account in web3 claiming k liyey
-        let auction_order_account_info = next_account_info(accounts)?; //fetch data and matach with web3
-        let auction_nft_token_account_info = next_account_info(accounts)?; //from auciton order Account in web3
-        let pda_account_info = next_account_info(accounts)?; //which holder the authority for NFT on Auction
-        let auction_nft_new_token_account = next_account_info(accounts)?; // new token account of user to send nft to
-        let auction_nft_mint = next_account_info(accounts)?; // auction TOken mint account for transfer function
+        let holder_info = next_account_info(accounts)?; // seller wallet
+        let token_account_info = next_account_info(accounts)?; // NFT for auction Wallet
+        let auction_order_account_info = next_account_info(accounts)?; // dutch auction data account
+        let token_program = next_account_info(accounts)?; // token program
+        let metadata_account = next_account_info(accounts)?;
+        let token_type_info = next_account_info(accounts)?; // payment mint, wrapped sol for sol
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        let nft_mint_account_info = next_account_info(accounts)?;
+        let allowlist_entry_info = next_account_info(accounts)?; // approved collection or creator
+        let market_info = Self::load_market_info(&program_id, market_info_account)?;
+        if *auction_order_account_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let metadata = Self::check_verified_metadata(
+            metadata_account,
+            token_account_info,
+            nft_mint_account_info,
+        )?;
+        Self::check_allowlisted(&program_id, &metadata, allowlist_entry_info)?;
+        let mut auction_order_struct: DutchAuctionOrder =
+            BorshDeserialize::try_from_slice(&auction_order_account_info.data.borrow())?;
+        if auction_order_struct.is_initialized {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        if floor_price == 0 || start_price <= floor_price || !holder_info.is_signer {
+            return Err(MarketError::MinPrice.into());
+        }
+        let now = Clock::get()?.unix_timestamp as u64;
+        Self::check_auction_duration(&market_info, now, end_time)?;
+        if decay == DecaySchedule::Stepwise && step_interval == 0 {
+            return Err(MarketError::InvalidInstruction.into());
+        }
+        if SPLS::Account::unpack_unchecked(&token_account_info.data.borrow())?.amount != 1 {
+            return Err(ProgramError::InsufficientFunds);
+        }
+        let (pda, _nonce) =
+            Pubkey::find_program_address(&[b"seeds_for_pda"], &program_id);
+        invoke(
+            &SPLIX::set_authority(
+                token_program.key,
+                token_account_info.key,
+                Some(&pda),
+                SPLIX::AuthorityType::AccountOwner,
+                holder_info.key,
+                &[holder_info.key],
+            )?,
+            &[
+                token_program.clone(),
+                token_account_info.clone(),
+                holder_info.clone(),
+            ],
+        )?;
+        auction_order_struct.is_initialized = true;
+        auction_order_struct.owner_wallet_address = *holder_info.key;
+        auction_order_struct.token_account = *token_account_info.key;
+        auction_order_struct.token_type = *token_type_info.key;
+        auction_order_struct.start_price = start_price;
+        auction_order_struct.floor_price = floor_price;
+        auction_order_struct.start_time = now;
+        auction_order_struct.end_time = end_time;
+        auction_order_struct.decay = decay;
+        auction_order_struct.step_interval = step_interval;
+        auction_order_struct
+            .serialize(&mut &mut auction_order_account_info.data.borrow_mut()[..])?;
+        Ok(())
+    }
+    fn buy_dutch(program_id: Pubkey, account_info: &[AccountInfo], max_price: u64) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let buyer_info = next_account_info(accounts)?; // buyer wallet
+        let holder_info = next_account_info(accounts)?; // seller wallet
+        let auction_order_account_info = next_account_info(accounts)?;
+        let auction_nft_token_account_info = next_account_info(accounts)?; // escrowed nft
+        let pda_account_info = next_account_info(accounts)?; // which holds the authority for the NFT
+        let auction_nft_new_token_account = next_account_info(accounts)?; // buyer's token account for the nft
+        let auction_nft_mint = next_account_info(accounts)?;
         let token_program = next_account_info(accounts)?;
-        let pda_spl_token_account_info = next_account_info(accounts)?; // to transfer to cat king spl token acount
-        let spl_mint_account_info = next_account_info(accounts)?; // zoin mint static value
-        let client_spl_token_account_info = next_account_info(accounts)?;
-        let auction_order_struct: AuctionOrder =
-            BorshDeserialize::try_from_slice(&mut auction_order_account_info.data.borrow())?;
+        let market_info_account = next_account_info(accounts)?; // marketplace config
         if *auction_order_account_info.owner != program_id {
             return Err(ProgramError::IncorrectProgramId);
         }
-        if holder_info.is_signer != true
+        let market_info = Self::load_market_info(&program_id, market_info_account)?;
+        let auction_order_struct: DutchAuctionOrder =
+            BorshDeserialize::try_from_slice(&auction_order_account_info.data.borrow())?;
+        if !auction_order_struct.is_initialized
             || *holder_info.key != auction_order_struct.owner_wallet_address
             || *auction_nft_token_account_info.key != auction_order_struct.token_account
-            || *bidder_info.key != auction_order_struct.bidder_wallet_address
         {
             return Err(MarketError::ValueMisMatch.into());
         }
-        if SPLS::Account::unpack_unchecked(&mut client_spl_token_account_info.data.borrow())?.owner
-            != Pubkey::from_str("to_check_spl_address").unwrap()
-        {
+        if !buyer_info.is_signer {
             return Err(MarketError::WrongOwner.into());
         }
-        if SPLS::Account::unpack_unchecked(&mut auction_nft_new_token_account.data.borrow())?.owner
-            != *bidder_info.key
+        if *buyer_info.key == auction_order_struct.owner_wallet_address {
+            return Err(MarketError::OwnerCannotBid.into());
+        }
+        if SPLS::Account::unpack_unchecked(&auction_nft_new_token_account.data.borrow())?.owner
+            != *buyer_info.key
         {
             return Err(MarketError::WrongOwner.into());
         }
-
         let (pda, _nonce) =
             Pubkey::find_program_address(&[b"seeds_for_pda"], &program_id);
         if *pda_account_info.key != pda {
             return Err(MarketError::PdaError.into());
         }
-        //transferring spl to cat king
-        if auction_order_struct.bid == 0 {
-            return Err(MarketError::InvalidInstruction.into());
-        }
-        if let Err(error) = invoke_signed(
-            &SPLIX::transfer(
-                token_program.key,
-                pda_spl_token_account_info.key,
-                holder_spl_token_account.key,
-                &pda,
-                &[&pda],
-                (auction_order_struct.bid as f64 * 97.5 / 100.00) as u64,
-            )?,
-            &[
-                pda_spl_token_account_info.clone(),
-                spl_mint_account_info.clone(),
-                holder_spl_token_account.clone(),
-                pda_account_info.clone(),
-            ],
-            &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-        ) {
-            return Err(error);
+        let price =
+            dutch::current_price(&auction_order_struct, Clock::get()?.unix_timestamp as u64)?;
+        // the price can only have gone down since the buyer signed
+        if price > max_price {
+            return Err(MarketError::ValueMisMatch.into());
         }
-        if let Err(error) = invoke_signed(
-            &SPLIX::transfer(
-                token_program.key,
-                pda_spl_token_account_info.key,
-                client_spl_token_account_info.key,
-                &pda,
-                &[&pda],
-                (auction_order_struct.bid as f64 * 2.5 / 100.00) as u64,
-            )?,
-            &[
-                pda_spl_token_account_info.clone(),
-                spl_mint_account_info.clone(),
-                client_spl_token_account_info.clone(),
-                pda_account_info.clone(),
-            ],
-            &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-        ) {
-            return Err(error);
+        let payment = Self::payment_mint(&auction_order_struct.token_type);
+        let (
+            buyer_payment_account_info,
+            holder_payment_account_info,
+            treasury_payment_account_info,
+            payment_program,
+        ) = match payment {
+            PaymentMint::Native => {
+                let king_wallet_account_info = next_account_info(accounts)?; // treasury
+                let sys_program_info = next_account_info(accounts)?;
+                (
+                    buyer_info,
+                    holder_info,
+                    king_wallet_account_info,
+                    sys_program_info,
+                )
+            }
+            PaymentMint::Spl(_) => {
+                let buyer_spl_token_account_info = next_account_info(accounts)?;
+                let spl_mint_account_info = next_account_info(accounts)?;
+                let holder_spl_token_account = next_account_info(accounts)?;
+                let client_spl_token_account_info = next_account_info(accounts)?; // treasury token account
+                if *spl_mint_account_info.key != auction_order_struct.token_type {
+                    return Err(MarketError::ValueMisMatch.into());
+                }
+                (
+                    buyer_spl_token_account_info,
+                    holder_spl_token_account,
+                    client_spl_token_account_info,
+                    token_program,
+                )
+            }
+        };
+        let metadata_account = next_account_info(accounts)?; // nft metadata, creator payment accounts follow
+        Self::check_payment_account(&payment, buyer_payment_account_info, buyer_info.key)?;
+        Self::check_payment_account(&payment, holder_payment_account_info, holder_info.key)?;
+        Self::check_treasury_account(&payment, treasury_payment_account_info, &market_info)?;
+        if SPLS::Account::unpack_unchecked(&auction_nft_token_account_info.data.borrow())?.mint
+            != *auction_nft_mint.key
+        {
+            return Err(MarketError::ValueMisMatch.into());
         }
-        // transfering NFT to bidder when they claim
-        if let Err(error) = invoke_signed(
+        let metadata = Self::load_nft_metadata(metadata_account, auction_nft_mint.key)?;
+        let (seller_amount, fee_amount) =
+            fees::split_amount(price, market_info.auction_fee_basis_points)?;
+        let royalty =
+            fees::royalty_amount(price, fee_amount, metadata.data.seller_fee_basis_points)?;
+        let royalty_paid = Self::pay_creator_royalties(
+            &payment,
+            payment_program,
+            buyer_payment_account_info,
+            buyer_info,
+            accounts,
+            &metadata,
+            royalty,
+            &[],
+        )?;
+        let seller_amount = seller_amount
+            .checked_sub(royalty_paid)
+            .ok_or(MarketError::MathOverflow)?;
+        Self::transfer_payment(
+            &payment,
+            payment_program,
+            buyer_payment_account_info,
+            holder_payment_account_info,
+            buyer_info,
+            seller_amount,
+            &[],
+        )?;
+        Self::transfer_payment(
+            &payment,
+            payment_program,
+            buyer_payment_account_info,
+            treasury_payment_account_info,
+            buyer_info,
+            fee_amount,
+            &[],
+        )?;
+        // transfering NFT to the buyer straight away
+        invoke_signed(
             &SPLIX::transfer_checked(
                 token_program.key,
                 auction_nft_token_account_info.key,
@@ -520,10 +1583,8 @@ impl Processor {
                 pda_account_info.clone(),
             ],
             &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-        ) {
-            return Err(error);
-        }
-        if let Err(error) = invoke_signed(
+        )?;
+        invoke_signed(
             &SPLIX::close_account(
                 token_program.key,
                 auction_nft_token_account_info.key,
@@ -537,240 +1598,161 @@ impl Processor {
                 pda_account_info.clone(),
             ],
             &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-        ) {
-            return Err(error);
-        }
+        )?;
         //closing the auction order account
-        **holder_info.try_borrow_mut_lamports()? = holder_info
-            .lamports()
-            .checked_add(auction_order_account_info.lamports())
-            .ok_or(ProgramError::InsufficientFunds)?;
-        **auction_order_account_info.try_borrow_mut_lamports()? = 0;
-        *auction_order_account_info.try_borrow_mut_data()? = &mut [];
+        Self::close_program_account(auction_order_account_info, holder_info)?;
         Ok(())
     }
-    fn complete_auction_order_user(
-        program_id: Pubkey,
-        account_info: &[AccountInfo],
-    ) -> ProgramResult {
+    // the seller can pull an unsold dutch auction at any time
+    fn cancel_dutch_auction(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
         let accounts = &mut account_info.iter();
-        let holder_info = next_account_info(accounts)?; //holder info
-        let holder_spl_token_account = next_account_info(accounts)?; //can be a static account value for now to transfer the bid amount
-        let bidder_info = next_account_info(accounts)?; //get from sellerOrder Account in web3 claiming k liyey
-        let auction_order_account_info = next_account_info(accounts)?; //fetch data and matach with web3
-        let auction_nft_token_account_info = next_account_info(accounts)?; //from auciton order Account in web3
-        let pda_account_info = next_account_info(accounts)?; //which holder the authority for NFT on Auction
-        let auction_nft_new_token_account = next_account_info(accounts)?; // new token account of user to send nft to
-        let auction_nft_mint = next_account_info(accounts)?; // auction TOken mint account for transfer function
+        let holder_info = next_account_info(accounts)?; // seller wallet
+        let token_account_info = next_account_info(accounts)?; // escrowed nft
+        let auction_order_account_info = next_account_info(accounts)?;
         let token_program = next_account_info(accounts)?;
-        let pda_spl_token_account_info = next_account_info(accounts)?; // to transfer to cat king spl token acount
-        let spl_mint_account_info = next_account_info(accounts)?; // zoin mint static value
-        let client_spl_token_account_info = next_account_info(accounts)?;
-        let auction_order_struct: AuctionOrder =
-            BorshDeserialize::try_from_slice(&mut auction_order_account_info.data.borrow())?;
+        let pda_account_info = next_account_info(accounts)?;
         if *auction_order_account_info.owner != program_id {
             return Err(ProgramError::IncorrectProgramId);
         }
-        if bidder_info.is_signer != true
-            || *holder_info.key != auction_order_struct.owner_wallet_address
-            || *auction_nft_token_account_info.key != auction_order_struct.token_account
-            || *bidder_info.key != auction_order_struct.bidder_wallet_address
-        {
-            return Err(MarketError::ValueMisMatch.into());
-        }
-        if SPLS::Account::unpack_unchecked(&mut client_spl_token_account_info.data.borrow())?.owner
-            != Pubkey::from_str("to_check_spl_address").unwrap()
-        {
-            return Err(MarketError::WrongOwner.into());
-        }
-
-        if SPLS::Account::unpack_unchecked(&mut auction_nft_new_token_account.data.borrow())?.owner
-            != *bidder_info.key
+        let auction_order_struct: DutchAuctionOrder =
+            BorshDeserialize::try_from_slice(&auction_order_account_info.data.borrow())?;
+        if *holder_info.key != auction_order_struct.owner_wallet_address
+            || *token_account_info.key != auction_order_struct.token_account
+            || !holder_info.is_signer
         {
-            return Err(MarketError::WrongOwner.into());
+            return Err(ProgramError::IllegalOwner);
         }
         let (pda, _nonce) =
             Pubkey::find_program_address(&[b"seeds_for_pda"], &program_id);
         if *pda_account_info.key != pda {
             return Err(MarketError::PdaError.into());
         }
-        //transferring spl to cat king
-        if Clock::get()?.unix_timestamp as u64 > auction_order_struct.time {
-            if let Err(error) = invoke_signed(
-                &SPLIX::transfer(
-                    token_program.key,
-                    pda_spl_token_account_info.key,
-                    holder_spl_token_account.key,
-                    &pda,
-                    &[&pda],
-                    (auction_order_struct.bid as f64 * 97.5 / 100.00) as u64,
-                )?,
-                &[
-                    pda_spl_token_account_info.clone(),
-                    spl_mint_account_info.clone(),
-                    holder_spl_token_account.clone(),
-                    pda_account_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-            if let Err(error) = invoke_signed(
-                &SPLIX::transfer(
-                    token_program.key,
-                    pda_spl_token_account_info.key,
-                    client_spl_token_account_info.key,
-                    &pda,
-                    &[&pda],
-                    (auction_order_struct.bid as f64 * 2.5 / 100.00) as u64,
-                )?,
-                &[
-                    pda_spl_token_account_info.clone(),
-                    spl_mint_account_info.clone(),
-                    client_spl_token_account_info.clone(),
-                    pda_account_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-            // transfering NFT to bidder when they claim
-            if let Err(error) = invoke_signed(
-                &SPLIX::transfer_checked(
-                    token_program.key,
-                    auction_nft_token_account_info.key,
-                    auction_nft_mint.key,
-                    auction_nft_new_token_account.key,
-                    &pda,
-                    &[&pda],
-                    1,
-                    0,
-                )?,
-                &[
-                    auction_nft_token_account_info.clone(),
-                    auction_nft_mint.clone(),
-                    auction_nft_new_token_account.clone(),
-                    pda_account_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-            if let Err(error) = invoke_signed(
-                &SPLIX::close_account(
-                    token_program.key,
-                    auction_nft_token_account_info.key,
-                    holder_info.key,
-                    &pda,
-                    &[&pda],
-                )?,
-                &[
-                    auction_nft_token_account_info.clone(),
-                    holder_info.clone(),
-                    pda_account_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-            //closing the auction order account
-            **holder_info.try_borrow_mut_lamports()? = holder_info
-                .lamports()
-                .checked_add(auction_order_account_info.lamports())
-                .ok_or(ProgramError::InsufficientFunds)?;
-            **auction_order_account_info.try_borrow_mut_lamports()? = 0;
-            *auction_order_account_info.try_borrow_mut_data()? = &mut [];
-        } else {
-            return Err(MarketError::AuctionNotEnded.into());
-        }
+        invoke_signed(
+            &SPLIX::set_authority(
+                token_program.key,
+                token_account_info.key,
+                Some(holder_info.key),
+                SPLIX::AuthorityType::AccountOwner,
+                &pda,
+                &[&pda],
+            )?,
+            &[
+                token_program.clone(),
+                token_account_info.clone(),
+                pda_account_info.clone(),
+            ],
+            &[&[&b"seeds_for_pda"[..], &[_nonce]]],
+        )?;
+        Self::close_program_account(auction_order_account_info, holder_info)?;
         Ok(())
     }
-    fn cancel_auction(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
-        let accounts = &mut account_info.iter();
-        let payer_info = next_account_info(accounts)?;
-        let token_account_info = next_account_info(accounts)?;
-        let auction_order_account_info = next_account_info(accounts)?;
-        let token_program = next_account_info(accounts)?;
-        let pda_account_info = next_account_info(accounts)?;
-        let bidder_info = next_account_info(accounts)?;
-        let previous_bidder_spl_token_account_info = next_account_info(accounts)?;
-        let spl_mint_account_info = next_account_info(accounts)?;
-        let pda_spl_token_account_info = next_account_info(accounts)?;
-        if *auction_order_account_info.owner != program_id {
+    // listings need a one of one mint whose metadata has a verified
+    // collection or a verified first creator
+    fn check_verified_metadata(
+        metadata_account: &AccountInfo,
+        token_account_info: &AccountInfo,
+        nft_mint_account_info: &AccountInfo,
+    ) -> Result<Metadata, ProgramError> {
+        let nft_mint = SPLS::Account::unpack_unchecked(&token_account_info.data.borrow())?.mint;
+        if *nft_mint_account_info.key != nft_mint {
+            return Err(MarketError::ValueMisMatch.into());
+        }
+        if *nft_mint_account_info.owner != spl_token::id() {
             return Err(ProgramError::IncorrectProgramId);
         }
-        let auction_order_struct: AuctionOrder =
-            BorshDeserialize::try_from_slice(&mut auction_order_account_info.data.borrow())?;
-        if payer_info.is_signer != true
-            || *payer_info.key != auction_order_struct.owner_wallet_address
-            || *token_account_info.key != auction_order_struct.token_account
-            || *bidder_info.key != auction_order_struct.bidder_wallet_address
-        {
-            return Err(ProgramError::IllegalOwner.into());
+        let mint = SPLS::Mint::unpack(&nft_mint_account_info.data.borrow())?;
+        if mint.supply != 1 || mint.decimals != 0 {
+            return Err(MarketError::NotAnNft.into());
         }
-        let (pda, _nonce) =
-            Pubkey::find_program_address(&[b"seeds_for_pda"], &program_id);
-        if *pda_account_info.key != pda {
-            return Err(MarketError::PdaError.into());
+        let metadata = Self::load_nft_metadata(metadata_account, &nft_mint)?;
+        if metadata.collection.is_none() && metadata.data.creators.is_none() {
+            return Err(MarketError::InvalidInstruction.into());
         }
-        if Clock::get()?.unix_timestamp as u64 > auction_order_struct.time {
-            if let Err(error) = invoke_signed(
-                &SPLIX::set_authority(
-                    token_program.key,
-                    token_account_info.key,
-                    Some(&payer_info.key),
-                    SPLIX::AuthorityType::AccountOwner,
-                    &pda,
-                    &[&pda],
-                )?,
-                &[
-                    token_program.clone(),
-                    token_account_info.clone(),
-                    pda_account_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-            ) {
-                return Err(error);
+        let mut found = 0;
+        if let Some(collection) = &metadata.collection {
+            if collection.verified {
+                found += 1;
             }
-            if auction_order_struct.bid != 0 {
-                if let Err(error) = invoke_signed(
-                    &SPLIX::transfer(
-                        token_program.key,
-                        pda_spl_token_account_info.key,
-                        previous_bidder_spl_token_account_info.key,
-                        &pda,
-                        &[&pda],
-                        auction_order_struct.bid,
-                    )?,
-                    &[
-                        pda_spl_token_account_info.clone(),
-                        spl_mint_account_info.clone(),
-                        previous_bidder_spl_token_account_info.clone(),
-                        pda_account_info.clone(),
-                    ],
-                    &[&[&b"seeds_for_pda"[..], &[_nonce]]],
-                ) {
-                    return Err(error);
-                }
+        }
+        if let Some(creators) = &metadata.data.creators {
+            if creators.first().filter(|creator| creator.verified).is_some() {
+                found += 1;
             }
-            **payer_info.try_borrow_mut_lamports()? = payer_info
-                .lamports()
-                .checked_add(auction_order_account_info.lamports())
-                .ok_or(ProgramError::InsufficientFunds)?;
-            **auction_order_account_info.try_borrow_mut_lamports()? = 0;
-            *auction_order_account_info.try_borrow_mut_data()? = &mut [];
-        } else {
-            return Err(MarketError::CannotCancel.into());
         }
-        Ok(())
+        if found == 0 {
+            return Err(MarketError::UnverifiedNFT.into());
+        }
+        Ok(metadata)
     }
-
-    //sol auctions
-    fn auction_order_sol(
+    fn verified_collection(metadata: &Metadata) -> Option<Pubkey> {
+        metadata
+            .collection
+            .as_ref()
+            .filter(|collection| collection.verified)
+            .map(|collection| collection.key)
+    }
+    // the metadata account has to be the one metaplex derives for the nft mint
+    fn load_nft_metadata(
+        metadata_account: &AccountInfo,
+        nft_mint: &Pubkey,
+    ) -> Result<Metadata, ProgramError> {
+        let (metadata_key, _bump) = find_metadata_account(nft_mint);
+        if *metadata_account.key != metadata_key
+            || *metadata_account.owner != mpl_token_metadata::id()
+        {
+            return Err(MarketError::ValueMisMatch.into());
+        }
+        let metadata = Metadata::from_account_info(metadata_account)?;
+        if metadata.mint != *nft_mint {
+            return Err(MarketError::ValueMisMatch.into());
+        }
+        Ok(metadata)
+    }
+    // each verified creator gets their share of the royalty, their payment
+    // accounts follow in the metadata's creator order; returns what was paid
+    #[allow(clippy::too_many_arguments)]
+    fn pay_creator_royalties<'a>(
+        payment: &PaymentMint,
+        payment_program: &AccountInfo<'a>,
+        from: &AccountInfo<'a>,
+        authority: &AccountInfo<'a>,
+        creator_accounts: &mut std::slice::Iter<AccountInfo<'a>>,
+        metadata: &Metadata,
+        royalty: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<u64, ProgramError> {
+        let mut paid: u64 = 0;
+        for creator in metadata
+            .data
+            .creators
+            .iter()
+            .flatten()
+            .filter(|creator| creator.verified)
+        {
+            let creator_account_info = next_account_info(creator_accounts)?;
+            Self::check_payment_account(payment, creator_account_info, &creator.address)?;
+            let amount = fees::creator_share(royalty, creator.share)?;
+            Self::transfer_payment(
+                payment,
+                payment_program,
+                from,
+                creator_account_info,
+                authority,
+                amount,
+                signer_seeds,
+            )?;
+            paid = paid.checked_add(amount).ok_or(MarketError::MathOverflow)?;
+        }
+        Ok(paid)
+    }
+    // sealed-bid auction paid in sol, bids stay hidden until the reveal window
+    fn sealed_auction_order(
         program_id: Pubkey,
         account_info: &[AccountInfo],
         minimum_price: u64,
-        time: u64,
+        bid_end_time: u64,
+        reveal_end_time: u64,
+        is_second_price: bool,
     ) -> ProgramResult {
         let accounts = &mut account_info.iter();
         let holder_info = next_account_info(accounts)?;
@@ -778,53 +1760,38 @@ impl Processor {
         let auction_order_account_info = next_account_info(accounts)?;
         let token_program = next_account_info(accounts)?;
         let metadata_account = next_account_info(accounts)?;
-        let metadata = Metadata::from_account_info(metadata_account)?;
-        let mut found = 0;
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        let nft_mint_account_info = next_account_info(accounts)?;
+        let allowlist_entry_info = next_account_info(accounts)?; // approved collection or creator
+        let market_info = Self::load_market_info(&program_id, market_info_account)?;
         if *auction_order_account_info.owner != program_id {
             return Err(ProgramError::IncorrectProgramId);
         }
-        if metadata.collection.is_some() || metadata.data.creators.is_some() {
-            if let Some(collection) = metadata.collection {
-                if collection.verified == true {
-                    found += 1;
-                }
-            }
-            if let Some(creators) = metadata.data.creators {
-                if creators[0].verified == true {
-                    found += 1;
-                }
-            }
-        } else {
-            return Err(MarketError::InvalidInstruction.into());
-        }
-        if found == 0 {
-            return Err(MarketError::UnverifiedNFT.into());
-        }
-        let mut auction_order_struct: AuctionOrderSol =
-            BorshDeserialize::try_from_slice(&mut auction_order_account_info.data.borrow())?;
-        if auction_order_struct.is_initialized == true {
+        let metadata = Self::check_verified_metadata(
+            metadata_account,
+            token_account_info,
+            nft_mint_account_info,
+        )?;
+        Self::check_allowlisted(&program_id, &metadata, allowlist_entry_info)?;
+        let mut auction_order_struct: SealedAuctionOrder =
+            BorshDeserialize::try_from_slice(&auction_order_account_info.data.borrow())?;
+        if auction_order_struct.is_initialized {
             return Err(ProgramError::AccountAlreadyInitialized);
         }
-        auction_order_struct.owner_wallet_address = *holder_info.key;
-        auction_order_struct.token_account = *token_account_info.key;
-        if Clock::get()?.unix_timestamp as u64 > time {
-            return Err(MarketError::InvalidInstruction.into());
+        if minimum_price == 0 || !holder_info.is_signer {
+            return Err(MarketError::MinPrice.into());
         }
-        if (time - Clock::get()?.unix_timestamp as u64) < 604800 {
-            auction_order_struct.time = time;
-        } else {
-            return Err(MarketError::MaxTimeLimit.into());
+        let now = Clock::get()?.unix_timestamp as u64;
+        if bid_end_time <= now || reveal_end_time <= bid_end_time {
+            return Err(MarketError::InvalidInstruction.into());
         }
-        auction_order_struct.bid = 0;
-        if SPLS::Account::unpack_unchecked(&mut token_account_info.data.borrow())?.amount != 1 {
+        Self::check_auction_duration(&market_info, now, reveal_end_time)?;
+        if SPLS::Account::unpack_unchecked(&token_account_info.data.borrow())?.amount != 1 {
             return Err(ProgramError::InsufficientFunds);
         }
-        if minimum_price as f64 / 1000000000.00 <= 0.00 || holder_info.is_signer != true {
-            return Err(MarketError::MinPrice.into());
-        }
         let (pda, _nonce) =
             Pubkey::find_program_address(&[b"seeds_for_pda$0!"], &program_id);
-        if let Err(error) = invoke(
+        invoke(
             &SPLIX::set_authority(
                 token_program.key,
                 token_account_info.key,
@@ -838,550 +1805,444 @@ impl Processor {
                 token_account_info.clone(),
                 holder_info.clone(),
             ],
-        ) {
-            return Err(error);
-        }
+        )?;
+        auction_order_struct.is_initialized = true;
+        auction_order_struct.owner_wallet_address = *holder_info.key;
+        auction_order_struct.token_account = *token_account_info.key;
         auction_order_struct.minimum_price = minimum_price;
-        auction_order_struct.total_bid_amount = 0;
+        auction_order_struct.bid_end_time = bid_end_time;
+        auction_order_struct.reveal_end_time = reveal_end_time;
+        auction_order_struct.is_second_price = is_second_price;
         auction_order_struct
             .serialize(&mut &mut auction_order_account_info.data.borrow_mut()[..])?;
         Ok(())
     }
-    fn place_bid_sol(program_id: Pubkey, account_info: &[AccountInfo], bid: u64) -> ProgramResult {
-        let accounts = &mut account_info.iter();
-        let bidder_account_info = next_account_info(accounts)?;
-        let auction_order_account_info = next_account_info(accounts)?;
-        let previous_bidder = next_account_info(accounts)?;
-        let sys_program_info = next_account_info(accounts)?;
-        let pda_account_info = next_account_info(accounts)?;
-        if *auction_order_account_info.owner != program_id {
-            return Err(ProgramError::IncorrectProgramId);
-        }
-        let mut auction_order_struct: AuctionOrderSol =
-            BorshDeserialize::try_from_slice(&mut auction_order_account_info.data.borrow())?;
-        if auction_order_struct.owner_wallet_address == *bidder_account_info.key {
-            return Err(MarketError::OwnerCannotBid.into());
-        }
-        let bid_increment = ((auction_order_struct.total_bid_amount as f64 * 5.00) / 100.00) as u64;
-        if bid < bid_increment + auction_order_struct.bid {
-            return Err(MarketError::BidMustBeGreater.into());
-        }
-        let (pda, _nonce) =
-            Pubkey::find_program_address(&[b"seeds_for_pda$0!"], &program_id);
-        if auction_order_struct.time - Clock::get()?.unix_timestamp as u64 <= 120 {
-            auction_order_struct.time += 120;
-        }
-        if &pda != pda_account_info.key {
-            return Err(MarketError::PdaError.into());
-        }
-        if bid as f64 > auction_order_struct.minimum_price as f64
-            && auction_order_struct.bid == 0
-            && (Clock::get()?.unix_timestamp as u64) < auction_order_struct.time
-        {
-            if let Err(error) = invoke(
-                &transfer(bidder_account_info.key, &pda, (bid as f64) as u64),
-                &[
-                    bidder_account_info.clone(),
-                    pda_account_info.clone(),
-                    sys_program_info.clone(),
-                ],
-            ) {
-                return Err(error);
-            }
-            auction_order_struct.bidder_wallet_address = *bidder_account_info.key;
-            auction_order_struct.bid = bid;
-            auction_order_struct.total_bid_amount = bid;
-        } else if bid > auction_order_struct.bid
-            && (Clock::get()?.unix_timestamp as u64) < auction_order_struct.time
-            && auction_order_struct.minimum_price < bid
-        {
-            if *previous_bidder.key != auction_order_struct.bidder_wallet_address {
-                return Err(ProgramError::IllegalOwner);
-            }
-
-            if let Err(error) = invoke(
-                &transfer(bidder_account_info.key, &pda, bid),
-                &[
-                    bidder_account_info.clone(),
-                    pda_account_info.clone(),
-                    sys_program_info.clone(),
-                ],
-            ) {
-                return Err(error);
-            }
-            //refunding previous bidder
-            if let Err(error) = invoke_signed(
-                &transfer(&pda, previous_bidder.key, auction_order_struct.bid),
-                &[
-                    pda_account_info.clone(),
-                    previous_bidder.clone(),
-                    sys_program_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-
-            auction_order_struct.bidder_wallet_address = *bidder_account_info.key;
-            auction_order_struct.bid = bid;
-            auction_order_struct.total_bid_amount = auction_order_struct.total_bid_amount + bid;
-        } else {
-            return Err(MarketError::BidMustBeGreater.into());
-        }
-
-        auction_order_struct
-            .serialize(&mut &mut auction_order_account_info.data.borrow_mut()[..])?;
-        Ok(())
-    }
-    fn complete_auction_order_sol(
+    // the deposit has to cover the hidden bid, it is all that gets escrowed
+    fn place_sealed_bid(
         program_id: Pubkey,
         account_info: &[AccountInfo],
+        commitment: [u8; 32],
+        deposit: u64,
     ) -> ProgramResult {
         let accounts = &mut account_info.iter();
-        let holder_info = next_account_info(accounts)?; //king
-        let bidder_info = next_account_info(accounts)?; //get from sellerOrder Account in web3 will claim themselves
+        let bidder_account_info = next_account_info(accounts)?;
         let auction_order_account_info = next_account_info(accounts)?;
-        let sell_token_account_info = next_account_info(accounts)?; //from sell order Account in web3
-        let sell_mint_account_info = next_account_info(accounts)?; //from sell order Account in web3
-        let sell_token_new_account_info = next_account_info(accounts)?; //from sell order Account in web3
-        let pda_account_info = next_account_info(accounts)?;
+        let bid_account_info = next_account_info(accounts)?; // bidder's sealed bid PDA
+        let pda_account_info = next_account_info(accounts)?; // sol escrow
         let sys_program_info = next_account_info(accounts)?;
-        let token_program = next_account_info(accounts)?;
         if *auction_order_account_info.owner != program_id {
             return Err(ProgramError::IncorrectProgramId);
         }
-        let auction_order_struct: AuctionOrderSol =
-            BorshDeserialize::try_from_slice(&mut auction_order_account_info.data.borrow())?;
-        if *holder_info.key != auction_order_struct.owner_wallet_address
-            || *sell_token_account_info.key != auction_order_struct.token_account
-            || *bidder_info.key != auction_order_struct.bidder_wallet_address
-            || bidder_info.is_signer != true
-            || auction_order_struct.bid == 0
-        {
+        let mut auction_order_struct: SealedAuctionOrder =
+            BorshDeserialize::try_from_slice(&auction_order_account_info.data.borrow())?;
+        if !auction_order_struct.is_initialized {
             return Err(MarketError::ValueMisMatch.into());
         }
-
-        let (pda, _nonce) =
-            Pubkey::find_program_address(&[b"seeds_for_pda$0!"], &program_id);
-        if SPLS::Account::unpack_unchecked(&mut sell_token_new_account_info.data.borrow())?.owner
-            != *bidder_info.key
-        {
-            return Err(MarketError::WrongOwner.into());
-        }
-        if *pda_account_info.key != pda {
-            return Err(MarketError::PdaError.into());
-        }
-        if (Clock::get()?.unix_timestamp as u64) > auction_order_struct.time
-            && auction_order_struct.bid != 0
-        {
-            if let Err(error) = invoke_signed(
-                &transfer(&pda, holder_info.key, auction_order_struct.bid),
-                &[
-                    sys_program_info.clone(),
-                    pda_account_info.clone(),
-                    holder_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-            if let Err(error) = invoke_signed(
-                &SPLIX::transfer_checked(
-                    token_program.key,
-                    sell_token_account_info.key,
-                    sell_mint_account_info.key,
-                    sell_token_new_account_info.key,
-                    &pda,
-                    &[&pda],
-                    1,
-                    0,
-                )?,
-                &[
-                    sell_token_account_info.clone(),
-                    sell_mint_account_info.clone(),
-                    sell_token_new_account_info.clone(),
-                    pda_account_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-            if let Err(error) = invoke_signed(
-                &SPLIX::close_account(
-                    token_program.key,
-                    sell_token_account_info.key,
-                    holder_info.key,
-                    &pda,
-                    &[&pda],
-                )?,
-                &[
-                    sell_token_account_info.clone(),
-                    holder_info.clone(),
-                    pda_account_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-            **holder_info.try_borrow_mut_lamports()? = holder_info
-                .lamports()
-                .checked_add(auction_order_account_info.lamports())
-                .ok_or(ProgramError::InsufficientFunds)?;
-            **auction_order_account_info.try_borrow_mut_lamports()? = 0;
-            *auction_order_account_info.try_borrow_mut_data()? = &mut [];
-        } else {
-            return Err(MarketError::AuctionNotEnded.into());
-        }
-        Ok(())
-    }
-    fn complete_auction_order_sol_anytime(
-        program_id: Pubkey,
-        account_info: &[AccountInfo],
-    ) -> ProgramResult {
-        let accounts = &mut account_info.iter();
-        let holder_info = next_account_info(accounts)?;
-        let bidder_info = next_account_info(accounts)?; //get from sellerOrder Account in web3
-        let auction_order_account_info = next_account_info(accounts)?;
-        let sell_token_account_info = next_account_info(accounts)?; //from sell order Account in web3
-        let sell_mint_account_info = next_account_info(accounts)?; //from sell order Account in web3
-        let sell_token_new_account_info = next_account_info(accounts)?; //from sell order Account in web3
-        let pda_account_info = next_account_info(accounts)?;
-        let sys_program_info = next_account_info(accounts)?;
-        let token_program = next_account_info(accounts)?;
-        let king = next_account_info(accounts)?; //2 percentages
-        if *auction_order_account_info.owner != program_id {
-            return Err(ProgramError::IncorrectProgramId);
-        }
-        let auction_order_struct: AuctionOrderSol =
-            BorshDeserialize::try_from_slice(&mut auction_order_account_info.data.borrow())?;
-        if *holder_info.key != auction_order_struct.owner_wallet_address
-            || *sell_token_account_info.key != auction_order_struct.token_account
-            || *bidder_info.key != auction_order_struct.bidder_wallet_address
-            || holder_info.is_signer != true
-            || auction_order_struct.bid == 0
-        {
-            return Err(MarketError::ValueMisMatch.into());
+        if auction_order_struct.owner_wallet_address == *bidder_account_info.key {
+            return Err(MarketError::OwnerCannotBid.into());
         }
-        if king.key
-            != &Pubkey::from_str("to_check_spl_address").unwrap()
-        {
+        if !bidder_account_info.is_signer {
             return Err(MarketError::WrongOwner.into());
         }
-        if SPLS::Account::unpack_unchecked(&mut sell_token_new_account_info.data.borrow())?.owner
-            != *bidder_info.key
-        {
-            return Err(MarketError::WrongOwner.into());
+        if Clock::get()?.unix_timestamp as u64 > auction_order_struct.bid_end_time {
+            return Err(MarketError::BiddingClosed.into());
+        }
+        if deposit < auction_order_struct.minimum_price {
+            return Err(MarketError::BidMustBeGreater.into());
         }
         let (pda, _nonce) =
             Pubkey::find_program_address(&[b"seeds_for_pda$0!"], &program_id);
-
         if *pda_account_info.key != pda {
             return Err(MarketError::PdaError.into());
         }
-        if auction_order_struct.bid == 0 {
-            return Err(MarketError::InvalidInstruction.into());
-        }
-        if let Err(error) = invoke_signed(
-            &transfer(
-                &pda,
-                holder_info.key,
-                ((auction_order_struct.bid as f64 * 97.5) / 100.00) as u64,
-            ),
+        let (bid_pda, bid_nonce) = Pubkey::find_program_address(
             &[
-                sys_program_info.clone(),
-                pda_account_info.clone(),
-                holder_info.clone(),
+                b"sealed_bid",
+                auction_order_account_info.key.as_ref(),
+                bidder_account_info.key.as_ref(),
             ],
-            &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-        ) {
-            return Err(error);
+            &program_id,
+        );
+        if *bid_account_info.key != bid_pda {
+            return Err(MarketError::PdaError.into());
         }
-        if let Err(error) = invoke_signed(
-            &transfer(
-                &pda,
-                king.key,
-                ((auction_order_struct.bid as f64 * 2.5) / 100.00) as u64,
+        // one sealed bid per wallet, create_account fails if it already exists
+        let bid_struct = SealedBid {
+            is_initialized: true,
+            auction_account: *auction_order_account_info.key,
+            bidder_wallet_address: *bidder_account_info.key,
+            commitment,
+            deposit,
+            is_revealed: false,
+            revealed_bid: 0,
+        };
+        let space = get_instance_packed_len(&bid_struct)?;
+        invoke_signed(
+            &system_instruction::create_account(
+                bidder_account_info.key,
+                bid_account_info.key,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                &program_id,
             ),
             &[
+                bidder_account_info.clone(),
+                bid_account_info.clone(),
                 sys_program_info.clone(),
-                pda_account_info.clone(),
-                king.clone(),
             ],
-            &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-        ) {
-            return Err(error);
-        }
-        if let Err(error) = invoke_signed(
-            &SPLIX::transfer_checked(
-                token_program.key,
-                sell_token_account_info.key,
-                sell_mint_account_info.key,
-                sell_token_new_account_info.key,
-                &pda,
-                &[&pda],
-                1,
-                0,
-            )?,
+            &[&[
+                &b"sealed_bid"[..],
+                auction_order_account_info.key.as_ref(),
+                bidder_account_info.key.as_ref(),
+                &[bid_nonce],
+            ]],
+        )?;
+        bid_struct.serialize(&mut &mut bid_account_info.data.borrow_mut()[..])?;
+        invoke(
+            &transfer(bidder_account_info.key, &pda, deposit),
             &[
-                sell_token_account_info.clone(),
-                sell_mint_account_info.clone(),
-                sell_token_new_account_info.clone(),
-                pda_account_info.clone(),
-            ],
-            &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-        ) {
-            return Err(error);
-        }
-        if let Err(error) = invoke_signed(
-            &SPLIX::close_account(
-                token_program.key,
-                sell_token_account_info.key,
-                holder_info.key,
-                &pda,
-                &[&pda],
-            )?,
-            &[
-                sell_token_account_info.clone(),
-                holder_info.clone(),
+                bidder_account_info.clone(),
                 pda_account_info.clone(),
+                sys_program_info.clone(),
             ],
-            &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-        ) {
-            return Err(error);
-        }
-        **holder_info.try_borrow_mut_lamports()? = holder_info
-            .lamports()
-            .checked_add(auction_order_account_info.lamports())
-            .ok_or(ProgramError::InsufficientFunds)?;
-        **auction_order_account_info.try_borrow_mut_lamports()? = 0;
-        *auction_order_account_info.try_borrow_mut_data()? = &mut [];
+        )?;
+        auction_order_struct.bid_count += 1;
+        auction_order_struct
+            .serialize(&mut &mut auction_order_account_info.data.borrow_mut()[..])?;
         Ok(())
     }
-    fn complete_auction_order_sol_user(
+    // a reveal above the deposit or under the minimum is recorded but can't win
+    fn reveal_sealed_bid(
         program_id: Pubkey,
         account_info: &[AccountInfo],
-    ) -> ProgramResult {
-        let accounts = &mut account_info.iter();
-        let holder_info = next_account_info(accounts)?;
-        let bidder_info = next_account_info(accounts)?; //get from sellerOrder Account in web3
-        let auction_order_account_info = next_account_info(accounts)?;
-        let sell_token_account_info = next_account_info(accounts)?; //from sell order Account in web3
-        let sell_mint_account_info = next_account_info(accounts)?; //from sell order Account in web3
-        let sell_token_new_account_info = next_account_info(accounts)?; //from sell order Account in web3
-        let pda_account_info = next_account_info(accounts)?;
-        let sys_program_info = next_account_info(accounts)?;
-        let token_program = next_account_info(accounts)?;
-        let king = next_account_info(accounts)?; //2 percentages
-        if *auction_order_account_info.owner != program_id {
-            return Err(ProgramError::IncorrectProgramId);
-        }
-        let auction_order_struct: AuctionOrderSol =
-            BorshDeserialize::try_from_slice(&mut auction_order_account_info.data.borrow())?;
-        if *holder_info.key != auction_order_struct.owner_wallet_address
-            || *sell_token_account_info.key != auction_order_struct.token_account
-            || *bidder_info.key != auction_order_struct.bidder_wallet_address
-            || bidder_info.is_signer != true
-            || auction_order_struct.bid == 0
-        {
-            return Err(MarketError::ValueMisMatch.into());
-        }
-        if king.key
-            != &Pubkey::from_str("to_check_spl_address").unwrap()
-        {
-            return Err(MarketError::WrongOwner.into());
-        }
-        if SPLS::Account::unpack_unchecked(&mut sell_token_new_account_info.data.borrow())?.owner
-            != *bidder_info.key
-        {
-            return Err(MarketError::WrongOwner.into());
-        }
-
-        let (pda, _nonce) =
-            Pubkey::find_program_address(&[b"seeds_for_pda$0!"], &program_id);
-
-        if *pda_account_info.key != pda {
-            return Err(MarketError::PdaError.into());
-        }
-        if (Clock::get()?.unix_timestamp as u64) > auction_order_struct.time
-            && auction_order_struct.bid != 0
-        {
-            if let Err(error) = invoke_signed(
-                &transfer(
-                    &pda,
-                    holder_info.key,
-                    ((auction_order_struct.bid as f64 * 97.5) / 100.00) as u64,
-                ),
-                &[
-                    sys_program_info.clone(),
-                    pda_account_info.clone(),
-                    holder_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-            if let Err(error) = invoke_signed(
-                &transfer(
-                    &pda,
-                    king.key,
-                    ((auction_order_struct.bid as f64 * 2.5) / 100.00) as u64,
-                ),
-                &[
-                    sys_program_info.clone(),
-                    pda_account_info.clone(),
-                    king.clone(),
-                ],
-                &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-            if let Err(error) = invoke_signed(
-                &SPLIX::transfer_checked(
-                    token_program.key,
-                    sell_token_account_info.key,
-                    sell_mint_account_info.key,
-                    sell_token_new_account_info.key,
-                    &pda,
-                    &[&pda],
-                    1,
-                    0,
-                )?,
-                &[
-                    sell_token_account_info.clone(),
-                    sell_mint_account_info.clone(),
-                    sell_token_new_account_info.clone(),
-                    pda_account_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-            if let Err(error) = invoke_signed(
-                &SPLIX::close_account(
-                    token_program.key,
-                    sell_token_account_info.key,
-                    holder_info.key,
-                    &pda,
-                    &[&pda],
-                )?,
-                &[
-                    sell_token_account_info.clone(),
-                    holder_info.clone(),
-                    pda_account_info.clone(),
-                ],
-                &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-            ) {
-                return Err(error);
+        bid: u64,
+        salt: [u8; 32],
+    ) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let bidder_account_info = next_account_info(accounts)?;
+        let auction_order_account_info = next_account_info(accounts)?;
+        let bid_account_info = next_account_info(accounts)?;
+        if *auction_order_account_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut auction_order_struct: SealedAuctionOrder =
+            BorshDeserialize::try_from_slice(&auction_order_account_info.data.borrow())?;
+        let mut bid_struct = Self::load_sealed_bid(
+            &program_id,
+            auction_order_account_info.key,
+            bid_account_info,
+        )?;
+        if !bidder_account_info.is_signer
+            || *bidder_account_info.key != bid_struct.bidder_wallet_address
+        {
+            return Err(MarketError::WrongOwner.into());
+        }
+        let now = Clock::get()?.unix_timestamp as u64;
+        if now <= auction_order_struct.bid_end_time
+            || now > auction_order_struct.reveal_end_time
+            || bid_struct.is_revealed
+        {
+            return Err(MarketError::NotInRevealWindow.into());
+        }
+        if hashv(&[&bid.to_le_bytes(), &salt, bidder_account_info.key.as_ref()]).to_bytes()
+            != bid_struct.commitment
+        {
+            return Err(MarketError::SeedMismatch.into());
+        }
+        bid_struct.is_revealed = true;
+        bid_struct.revealed_bid = bid;
+        // ties go to whoever revealed first
+        if bid <= bid_struct.deposit && bid >= auction_order_struct.minimum_price {
+            if bid > auction_order_struct.bid {
+                auction_order_struct.second_bid = auction_order_struct.bid;
+                auction_order_struct.bidder_wallet_address = *bidder_account_info.key;
+                auction_order_struct.bid = bid;
+            } else if bid > auction_order_struct.second_bid {
+                auction_order_struct.second_bid = bid;
             }
-            **holder_info.try_borrow_mut_lamports()? = holder_info
-                .lamports()
-                .checked_add(auction_order_account_info.lamports())
-                .ok_or(ProgramError::InsufficientFunds)?;
-            **auction_order_account_info.try_borrow_mut_lamports()? = 0;
-            *auction_order_account_info.try_borrow_mut_data()? = &mut [];
-        } else {
-            return Err(MarketError::AuctionNotEnded.into());
         }
+        bid_struct.serialize(&mut &mut bid_account_info.data.borrow_mut()[..])?;
+        auction_order_struct
+            .serialize(&mut &mut auction_order_account_info.data.borrow_mut()[..])?;
         Ok(())
     }
-    fn cancel_auction_sol(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
+    // anyone can settle once the reveal window is over. bids passed along as
+    // (bid account, bidder wallet) pairs are refunded or forfeited here, the
+    // rest through ReclaimSealedBid
+    fn complete_sealed_auction(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
         let accounts = &mut account_info.iter();
-        let payer_info = next_account_info(accounts)?;
-        let token_account_info = next_account_info(accounts)?;
+        let holder_info = next_account_info(accounts)?; // seller wallet
         let auction_order_account_info = next_account_info(accounts)?;
-        let token_program = next_account_info(accounts)?;
+        let sell_token_account_info = next_account_info(accounts)?; // escrowed nft
+        let sell_mint_account_info = next_account_info(accounts)?;
+        let sell_token_new_account_info = next_account_info(accounts)?; // winner's token account
         let pda_account_info = next_account_info(accounts)?;
         let sys_program_info = next_account_info(accounts)?;
-        let previous_bidder = next_account_info(accounts)?;
+        let token_program = next_account_info(accounts)?;
+        let king = next_account_info(accounts)?; // treasury
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        let metadata_account = next_account_info(accounts)?; // creator payment accounts follow when sold
         if *auction_order_account_info.owner != program_id {
             return Err(ProgramError::IncorrectProgramId);
         }
-        let auction_order_struct: AuctionOrderSol =
-            BorshDeserialize::try_from_slice(&mut auction_order_account_info.data.borrow())?;
-        if *payer_info.key != auction_order_struct.owner_wallet_address
-            || *token_account_info.key != auction_order_struct.token_account
-            || *previous_bidder.key != auction_order_struct.bidder_wallet_address
-            || payer_info.is_signer != true
+        let market_info = Self::load_market_info(&program_id, market_info_account)?;
+        let mut auction_order_struct: SealedAuctionOrder =
+            BorshDeserialize::try_from_slice(&auction_order_account_info.data.borrow())?;
+        if *holder_info.key != auction_order_struct.owner_wallet_address
+            || *sell_token_account_info.key != auction_order_struct.token_account
+            || auction_order_struct.is_settled
         {
-            return Err(ProgramError::IllegalOwner.into());
+            return Err(MarketError::ValueMisMatch.into());
+        }
+        if Clock::get()?.unix_timestamp as u64 <= auction_order_struct.reveal_end_time {
+            return Err(MarketError::AuctionNotEnded.into());
+        }
+        if *king.key != market_info.treasury_wallet_address {
+            return Err(MarketError::WrongOwner.into());
         }
         let (pda, _nonce) =
             Pubkey::find_program_address(&[b"seeds_for_pda$0!"], &program_id);
-
         if *pda_account_info.key != pda {
             return Err(MarketError::PdaError.into());
         }
-        // 16                            15
-        if Clock::get()?.unix_timestamp as u64 > auction_order_struct.time {
-            if let Err(error) = invoke_signed(
+        if auction_order_struct.bid == 0 {
+            // no valid reveal, the nft goes back to the seller
+            invoke_signed(
                 &SPLIX::set_authority(
                     token_program.key,
-                    token_account_info.key,
-                    Some(&payer_info.key),
+                    sell_token_account_info.key,
+                    Some(holder_info.key),
                     SPLIX::AuthorityType::AccountOwner,
                     &pda,
                     &[&pda],
                 )?,
                 &[
                     token_program.clone(),
-                    token_account_info.clone(),
+                    sell_token_account_info.clone(),
                     pda_account_info.clone(),
                 ],
                 &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-            ) {
-                return Err(error);
-            }
-            if auction_order_struct.bid != 0 {
-                //     if let Err(error) = invoke_signed(
-                //         &transfer(&pda, bidder_info.key, auction_order_struct.bid),
-                //         &[
-                //             pda_account_info.clone(),
-                //             bidder_info.clone(),
-                //             sys_program_info.clone(),
-                //         ],
-                //         &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-                //     ) {
-                //         return Err(error);
-                //     }
-                // }
-                if let Err(error) = invoke_signed(
-                    &transfer(&pda, previous_bidder.key, auction_order_struct.bid),
-                    &[
-                        pda_account_info.clone(),
-                        previous_bidder.clone(),
-                        sys_program_info.clone(),
-                    ],
-                    &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
-                ) {
-                    return Err(error);
-                }
-                **payer_info.try_borrow_mut_lamports()? = payer_info
-                    .lamports()
-                    .checked_add(auction_order_account_info.lamports())
-                    .ok_or(ProgramError::InsufficientFunds)?;
-                **auction_order_account_info.try_borrow_mut_lamports()? = 0;
-                *auction_order_account_info.try_borrow_mut_data()? = &mut [];
+            )?;
+        } else {
+            if SPLS::Account::unpack_unchecked(&sell_token_new_account_info.data.borrow())?.owner
+                != auction_order_struct.bidder_wallet_address
+            {
+                return Err(MarketError::WrongOwner.into());
             }
+            let sale_accounts = &[
+                holder_info.clone(),
+                holder_info.clone(),
+                king.clone(),
+                sell_token_account_info.clone(),
+                sell_mint_account_info.clone(),
+                sell_token_new_account_info.clone(),
+                pda_account_info.clone(),
+                pda_account_info.clone(),
+                token_program.clone(),
+                sys_program_info.clone(),
+                metadata_account.clone(),
+            ];
+            Self::settle_auction_sale(
+                &program_id,
+                &PaymentMint::Native,
+                sale_accounts,
+                accounts,
+                Self::sealed_clearing_price(&auction_order_struct),
+                market_info.auction_fee_basis_points,
+            )?;
+        }
+        auction_order_struct.is_settled = true;
+        while let Some(bid_account_info) = accounts.next() {
+            let bidder_info = next_account_info(accounts)?;
+            Self::settle_sealed_bid(
+                &program_id,
+                auction_order_account_info.key,
+                &mut auction_order_struct,
+                bid_account_info,
+                bidder_info,
+                holder_info,
+                pda_account_info,
+                sys_program_info,
+            )?;
+        }
+        Self::close_sealed_auction_if_settled(
+            auction_order_account_info,
+            holder_info,
+            &auction_order_struct,
+        )
+    }
+    fn reclaim_sealed_bid(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let bidder_info = next_account_info(accounts)?;
+        let auction_order_account_info = next_account_info(accounts)?;
+        let bid_account_info = next_account_info(accounts)?;
+        let holder_info = next_account_info(accounts)?; // seller, gets forfeited deposits
+        let pda_account_info = next_account_info(accounts)?; // sol escrow
+        let sys_program_info = next_account_info(accounts)?;
+        if *auction_order_account_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut auction_order_struct: SealedAuctionOrder =
+            BorshDeserialize::try_from_slice(&auction_order_account_info.data.borrow())?;
+        if !auction_order_struct.is_settled {
+            return Err(MarketError::AuctionNotEnded.into());
+        }
+        Self::settle_sealed_bid(
+            &program_id,
+            auction_order_account_info.key,
+            &mut auction_order_struct,
+            bid_account_info,
+            bidder_info,
+            holder_info,
+            pda_account_info,
+            sys_program_info,
+        )?;
+        Self::close_sealed_auction_if_settled(
+            auction_order_account_info,
+            holder_info,
+            &auction_order_struct,
+        )
+    }
+    // the winner gets back what their deposit holds over the price, other
+    // revealed bids get the whole deposit and unrevealed ones go to the seller
+    #[allow(clippy::too_many_arguments)]
+    fn settle_sealed_bid<'a>(
+        program_id: &Pubkey,
+        auction_account: &Pubkey,
+        auction_order_struct: &mut SealedAuctionOrder,
+        bid_account_info: &AccountInfo<'a>,
+        bidder_info: &AccountInfo<'a>,
+        holder_info: &AccountInfo<'a>,
+        pda_account_info: &AccountInfo<'a>,
+        sys_program_info: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let bid_struct = Self::load_sealed_bid(program_id, auction_account, bid_account_info)?;
+        if *bidder_info.key != bid_struct.bidder_wallet_address
+            || *holder_info.key != auction_order_struct.owner_wallet_address
+        {
+            return Err(MarketError::WrongOwner.into());
+        }
+        let (pda, _nonce) = Pubkey::find_program_address(&[b"seeds_for_pda$0!"], program_id);
+        if *pda_account_info.key != pda {
+            return Err(MarketError::PdaError.into());
+        }
+        let (receiver, amount) = if !bid_struct.is_revealed {
+            (holder_info, bid_struct.deposit)
+        } else if *bidder_info.key == auction_order_struct.bidder_wallet_address {
+            let change = bid_struct
+                .deposit
+                .checked_sub(Self::sealed_clearing_price(auction_order_struct))
+                .ok_or(MarketError::MathOverflow)?;
+            (bidder_info, change)
         } else {
-            return Err(MarketError::CannotCancel.into());
+            (bidder_info, bid_struct.deposit)
+        };
+        if amount > 0 {
+            invoke_signed(
+                &transfer(&pda, receiver.key, amount),
+                &[
+                    sys_program_info.clone(),
+                    pda_account_info.clone(),
+                    receiver.clone(),
+                ],
+                &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
+            )?;
+        }
+        //closing the bid account
+        Self::close_program_account(bid_account_info, bidder_info)?;
+        auction_order_struct.bids_settled += 1;
+        Ok(())
+    }
+    fn sealed_clearing_price(auction_order_struct: &SealedAuctionOrder) -> u64 {
+        if auction_order_struct.is_second_price {
+            auction_order_struct
+                .second_bid
+                .max(auction_order_struct.minimum_price)
+        } else {
+            auction_order_struct.bid
+        }
+    }
+    fn load_sealed_bid(
+        program_id: &Pubkey,
+        auction_account: &Pubkey,
+        bid_account_info: &AccountInfo,
+    ) -> Result<SealedBid, ProgramError> {
+        if *bid_account_info.owner != *program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let bid_struct: SealedBid = BorshDeserialize::try_from_slice(&bid_account_info.data.borrow())?;
+        let (bid_pda, _nonce) = Pubkey::find_program_address(
+            &[
+                b"sealed_bid",
+                auction_account.as_ref(),
+                bid_struct.bidder_wallet_address.as_ref(),
+            ],
+            program_id,
+        );
+        if *bid_account_info.key != bid_pda || !bid_struct.is_initialized {
+            return Err(MarketError::PdaError.into());
+        }
+        Ok(bid_struct)
+    }
+    // kept until every deposit has been paid back or forfeited
+    fn close_sealed_auction_if_settled(
+        auction_order_account_info: &AccountInfo,
+        holder_info: &AccountInfo,
+        auction_order_struct: &SealedAuctionOrder,
+    ) -> ProgramResult {
+        if auction_order_struct.bids_settled == auction_order_struct.bid_count {
+            Self::close_program_account(auction_order_account_info, holder_info)?;
+        } else {
+            auction_order_struct
+                .serialize(&mut &mut auction_order_account_info.data.borrow_mut()[..])?;
         }
-
         Ok(())
     }
     //RAFFLES
+    #[allow(clippy::too_many_arguments)]
     fn raffle_start(
         program_id: Pubkey,
         account_info: &[AccountInfo],
         time: u64,
         price: u64,
         total_ticket: u64,
+        seed_commitment: [u8; 32],
+        randomness_source: RandomnessSource,
+        randomness_account: Pubkey,
+        prize_count: u8,
+        min_tickets: u64,
+        max_tickets_per_wallet: u64,
     ) -> ProgramResult {
         let accounts = &mut account_info.iter();
         let holder_info = next_account_info(accounts)?;
-        let token_account_info = next_account_info(accounts)?;
+        let token_account_info = next_account_info(accounts)?; // first prize
         let raffle_order_account_info = next_account_info(accounts)?;
         let token_program = next_account_info(accounts)?; // token program
         let token_type = next_account_info(accounts)?; //
+        let metadata_account = next_account_info(accounts)?; // first prize's metadata
+        let nft_mint_account_info = next_account_info(accounts)?; // first prize's mint
+        let allowlist_entry_info = next_account_info(accounts)?; // approved collection or creator
+        if prize_count == 0 {
+            return Err(MarketError::InvalidInstruction.into());
+        }
+        let mut prize_token_accounts = vec![token_account_info];
+        let mut prize_collections = Vec::new();
+        for _ in 1..prize_count {
+            // lower tier prizes in order, each followed by its mint, metadata
+            // and allowlist entry
+            let prize_token_account_info = next_account_info(accounts)?;
+            let prize_mint_account_info = next_account_info(accounts)?;
+            let prize_metadata_account = next_account_info(accounts)?;
+            let prize_allowlist_entry_info = next_account_info(accounts)?;
+            let prize_metadata = Self::check_verified_metadata(
+                prize_metadata_account,
+                prize_token_account_info,
+                prize_mint_account_info,
+            )?;
+            Self::check_allowlisted(&program_id, &prize_metadata, prize_allowlist_entry_info)?;
+            prize_collections.push(Self::verified_collection(&prize_metadata));
+            prize_token_accounts.push(prize_token_account_info);
+        }
         let mut raffle_order_struct: RaffleOrder =
             try_from_slice_unchecked(&mut raffle_order_account_info.data.borrow())?;
         if raffle_order_struct.is_initialized == true {
@@ -1390,8 +2251,8 @@ impl Processor {
         if *raffle_order_account_info.owner != program_id {
             return Err(ProgramError::IncorrectProgramId);
         }
-        if account_info.len() == 6 {
-            let feature_raffle_account = next_account_info(accounts)?; //
+        // an account left after the prizes means the raffle is featured
+        if let Some(feature_raffle_account) = accounts.next() {
             if *feature_raffle_account.owner != program_id {
                 return Err(ProgramError::IncorrectProgramId);
             }
@@ -1405,38 +2266,88 @@ impl Processor {
             feature_account_data.raffle_account = *raffle_order_account_info.key;
             feature_account_data
                 .serialize(&mut &mut feature_raffle_account.data.borrow_mut()[..])?;
-        }
-        if SPLS::Account::unpack_unchecked(&mut token_account_info.data.borrow())?.amount != 1 {
-            return Err(ProgramError::InsufficientFunds);
+            raffle_order_struct.is_featured = true;
         }
         if price == 0 || holder_info.is_signer != true {
             return Err(MarketError::MinPrice.into());
         }
         raffle_order_struct.is_initialized = true;
         raffle_order_struct.owner_wallet_address = *holder_info.key;
-        raffle_order_struct.token_account = *token_account_info.key;
         raffle_order_struct.time = time;
         raffle_order_struct.price = price;
         raffle_order_struct.token_type = *token_type.key;
         raffle_order_struct.ticket_supply = total_ticket;
+        if min_tickets > total_ticket {
+            return Err(MarketError::InvalidInstruction.into());
+        }
+        raffle_order_struct.min_tickets = min_tickets;
+        raffle_order_struct.max_tickets_per_wallet = max_tickets_per_wallet;
+        match randomness_source {
+            RandomnessSource::CommitReveal => {}
+            RandomnessSource::Oracle => {
+                if randomness_account == Pubkey::default() {
+                    return Err(MarketError::InvalidInstruction.into());
+                }
+            }
+            #[cfg(feature = "local-randomness")]
+            RandomnessSource::Local => {}
+            #[cfg(not(feature = "local-randomness"))]
+            RandomnessSource::Local => return Err(MarketError::InvalidInstruction.into()),
+        }
+        raffle_order_struct.seed_commitment = seed_commitment;
+        raffle_order_struct.randomness_source = randomness_source;
+        raffle_order_struct.randomness_account = randomness_account;
         let (pda, _nonce) =
             Pubkey::find_program_address(&[b"seeds_for_pda"], &program_id);
-        if let Err(error) = invoke(
-            &SPLIX::set_authority(
-                token_program.key,
-                token_account_info.key,
-                Some(&pda),
-                SPLIX::AuthorityType::AccountOwner,
-                holder_info.key,
-                &[holder_info.key],
-            )?,
-            &[
-                token_program.clone(),
-                token_account_info.clone(),
-                holder_info.clone(),
-            ],
-        ) {
-            return Err(error);
+        // proceeds pay royalties to the creators of this mint's metadata, so
+        // lower prizes have to come from the same verified collection
+        let metadata = Self::check_verified_metadata(
+            metadata_account,
+            token_account_info,
+            nft_mint_account_info,
+        )?;
+        Self::check_allowlisted(&program_id, &metadata, allowlist_entry_info)?;
+        let collection = Self::verified_collection(&metadata);
+        if prize_collections
+            .iter()
+            .any(|prize_collection| collection.is_none() || *prize_collection != collection)
+        {
+            return Err(MarketError::PrizeCollectionMismatch.into());
+        }
+        raffle_order_struct.nft_mint = *nft_mint_account_info.key;
+        raffle_order_struct.prizes = Vec::with_capacity(prize_token_accounts.len());
+        for prize_token_account_info in prize_token_accounts {
+            if SPLS::Account::unpack_unchecked(&prize_token_account_info.data.borrow())?.amount != 1
+            {
+                return Err(ProgramError::InsufficientFunds);
+            }
+            if raffle_order_struct
+                .prizes
+                .iter()
+                .any(|prize| prize.token_account == *prize_token_account_info.key)
+            {
+                return Err(MarketError::ValueMisMatch.into());
+            }
+            invoke(
+                &SPLIX::set_authority(
+                    token_program.key,
+                    prize_token_account_info.key,
+                    Some(&pda),
+                    SPLIX::AuthorityType::AccountOwner,
+                    holder_info.key,
+                    &[holder_info.key],
+                )?,
+                &[
+                    token_program.clone(),
+                    prize_token_account_info.clone(),
+                    holder_info.clone(),
+                ],
+            )?;
+            raffle_order_struct.prizes.push(RafflePrize {
+                token_account: *prize_token_account_info.key,
+                winner_wallet_address: Pubkey::default(),
+                is_claimed: false,
+            });
         }
         raffle_order_struct.serialize(&mut &mut raffle_order_account_info.data.borrow_mut()[..])?;
         Ok(())
@@ -1450,99 +2361,384 @@ impl Processor {
         let accounts = &mut account_info.iter();
         let raffler_info = next_account_info(accounts)?; //cat king wallet
         let raffle_order_account_info = next_account_info(accounts)?; // auction data account
+        let ticket_account_info = next_account_info(accounts)?; // buyer's ticket PDA, created on first entry
+        let system_program_info = next_account_info(accounts)?;
         let mut raffle_struct: RaffleOrder =
             try_from_slice_unchecked(&mut raffle_order_account_info.data.borrow())?;
-        let mut exist = false;
         if *raffler_info.key == raffle_struct.owner_wallet_address {
             return Err(MarketError::OwnerCannotBid.into());
         }
         if *raffle_order_account_info.owner != program_id {
             return Err(ProgramError::IncorrectProgramId);
         }
-        if raffle_struct.raffle_entry_record.is_empty() == true {
-            if quantity as u64 <= raffle_struct.ticket_supply {
-                raffle_struct.raffle_entry_record.push(RaffleCounter {
-                    raffler_address: *raffler_info.key,
-                    entry_counter: quantity as u32,
-                });
-                exist = true;
-            } else {
-                return Err(MarketError::ValueMisMatch.into());
-            }
-        } else {
-            for i in 0..raffle_struct.raffle_entry_record.len() {
-                if *raffler_info.key == raffle_struct.raffle_entry_record[i].raffler_address {
-                    exist = true;
-                    raffle_struct.raffle_entry_record[i].entry_counter += quantity as u32;
-                    break;
-                }
-            }
-        }
-        if exist == false {
-            raffle_struct.raffle_entry_record.push(RaffleCounter {
-                raffler_address: *raffler_info.key,
-                entry_counter: quantity as u32,
-            });
-        }
-        let mut total = 0;
-        for i in 0..raffle_struct.raffle_entry_record.len() {
-            let temp = raffle_struct.raffle_entry_record[i].entry_counter;
-            total = total + temp;
+        if raffle_struct.is_drawn
+            || raffle_struct.is_cancelled
+            || Clock::get()?.unix_timestamp as u64 > raffle_struct.time
+        {
+            return Err(MarketError::RaffleEnded.into());
         }
-        if total as u64 > raffle_struct.ticket_supply {
+        if quantity == 0 {
             return Err(MarketError::ValueMisMatch.into());
         }
-        if amount != raffle_struct.price * quantity as u64 {
+        Self::record_raffle_tickets(
+            &program_id,
+            raffler_info,
+            raffle_order_account_info.key,
+            ticket_account_info,
+            system_program_info,
+            &mut raffle_struct,
+            quantity as u64,
+        )?;
+        if amount
+            != raffle_struct
+                .price
+                .checked_mul(quantity as u64)
+                .ok_or(MarketError::MathOverflow)?
+        {
             return Err(MarketError::ValueMisMatch.into());
         }
-        if raffle_struct.time > Clock::get()?.unix_timestamp as u64
-            && raffle_struct.token_type
-                != Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap()
+        // ticket money stays in escrow until the raffle is drawn or refunded
+        if raffle_struct.token_type
+            != Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap()
         {
             let raffler_spl_token_account_info = next_account_info(accounts)?;
             let spl_mint_account_info = next_account_info(accounts)?;
-            let king_spl_token_account = next_account_info(accounts)?;
+            let pda_spl_token_account_info = next_account_info(accounts)?; // escrow, owned by the pda
             let token_program = next_account_info(accounts)?; // token program
-            let client_spl_token_account_info = next_account_info(accounts)?; //ppublic owner
-
-            let spl_accounts = &[
-                raffler_info.clone(),
-                raffler_spl_token_account_info.clone(),
-                spl_mint_account_info.clone(),
-                king_spl_token_account.clone(),
-                token_program.clone(),
-                client_spl_token_account_info.clone(),
-            ];
-            if let Err(error) =
-                Self::handle_spl_tokens(spl_accounts, raffle_struct.owner_wallet_address, amount)
-            {
-                return Err(error);
+            let (pda, _nonce) =
+                Pubkey::find_program_address(&[b"seeds_for_pda"], &program_id);
+            Self::check_raffle_escrow(
+                &raffle_struct.token_type,
+                pda_spl_token_account_info,
+                spl_mint_account_info,
+                &pda,
+            )?;
+            invoke(
+                &SPLIX::transfer(
+                    token_program.key,
+                    raffler_spl_token_account_info.key,
+                    pda_spl_token_account_info.key,
+                    raffler_info.key,
+                    &[raffler_info.key],
+                    amount,
+                )?,
+                &[
+                    raffler_spl_token_account_info.clone(),
+                    spl_mint_account_info.clone(),
+                    pda_spl_token_account_info.clone(),
+                    raffler_info.clone(),
+                ],
+            )?;
+        } else {
+            let pda_account_info = next_account_info(accounts)?; // sol escrow
+            let (pda, _nonce) =
+                Pubkey::find_program_address(&[b"seeds_for_pda$0!"], &program_id);
+            if *pda_account_info.key != pda {
+                return Err(MarketError::PdaError.into());
+            }
+            invoke(
+                &transfer(raffler_info.key, &pda, amount),
+                &[
+                    raffler_info.clone(),
+                    pda_account_info.clone(),
+                    system_program_info.clone(),
+                ],
+            )?;
+        }
+        raffle_struct.serialize(&mut &mut raffle_order_account_info.data.borrow_mut()[..])?;
+        Ok(())
+    }
+    // hands the buyer the next `quantity` ticket numbers, growing their ticket
+    // account by one range unless it continues their previous purchase
+    fn record_raffle_tickets<'a>(
+        program_id: &Pubkey,
+        raffler_info: &AccountInfo<'a>,
+        raffle_account: &Pubkey,
+        ticket_account_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        raffle_struct: &mut RaffleOrder,
+        quantity: u64,
+    ) -> ProgramResult {
+        let start = raffle_struct.tickets_sold;
+        let end = start.checked_add(quantity).ok_or(MarketError::MathOverflow)?;
+        if end > raffle_struct.ticket_supply {
+            return Err(MarketError::ValueMisMatch.into());
+        }
+        let (ticket_pda, ticket_nonce) = Pubkey::find_program_address(
+            &[b"raffle_ticket", raffle_account.as_ref(), raffler_info.key.as_ref()],
+            program_id,
+        );
+        if *ticket_account_info.key != ticket_pda {
+            return Err(MarketError::PdaError.into());
+        }
+        let rent = Rent::get()?;
+        let ticket_struct = if ticket_account_info.data_is_empty() {
+            let ticket_struct = RaffleTicket {
+                is_initialized: true,
+                raffle_account: *raffle_account,
+                buyer_wallet_address: *raffler_info.key,
+                ticket_count: quantity,
+                ranges: vec![TicketRange { start, end }],
+            };
+            let space = get_instance_packed_len(&ticket_struct)?;
+            invoke_signed(
+                &system_instruction::create_account(
+                    raffler_info.key,
+                    ticket_account_info.key,
+                    rent.minimum_balance(space),
+                    space as u64,
+                    program_id,
+                ),
+                &[
+                    raffler_info.clone(),
+                    ticket_account_info.clone(),
+                    system_program_info.clone(),
+                ],
+                &[&[
+                    &b"raffle_ticket"[..],
+                    raffle_account.as_ref(),
+                    raffler_info.key.as_ref(),
+                    &[ticket_nonce],
+                ]],
+            )?;
+            raffle_struct.buyer_count += 1;
+            Self::check_wallet_ticket_limit(raffle_struct, &ticket_struct)?;
+            ticket_struct
+        } else {
+            if *ticket_account_info.owner != *program_id {
+                return Err(ProgramError::IncorrectProgramId);
             }
-        } else if raffle_struct.token_type
-            == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap()
+            let mut ticket_struct: RaffleTicket =
+                try_from_slice_unchecked(&ticket_account_info.data.borrow())?;
+            ticket_struct.ticket_count = ticket_struct
+                .ticket_count
+                .checked_add(quantity)
+                .ok_or(MarketError::MathOverflow)?;
+            Self::check_wallet_ticket_limit(raffle_struct, &ticket_struct)?;
+            match ticket_struct.ranges.last_mut() {
+                Some(range) if range.end == start => range.end = end,
+                _ => ticket_struct.ranges.push(TicketRange { start, end }),
+            }
+            let space = get_instance_packed_len(&ticket_struct)?;
+            if space > ticket_account_info.data_len() {
+                let top_up = rent
+                    .minimum_balance(space)
+                    .saturating_sub(ticket_account_info.lamports());
+                if top_up > 0 {
+                    invoke(
+                        &system_instruction::transfer(
+                            raffler_info.key,
+                            ticket_account_info.key,
+                            top_up,
+                        ),
+                        &[
+                            raffler_info.clone(),
+                            ticket_account_info.clone(),
+                            system_program_info.clone(),
+                        ],
+                    )?;
+                }
+                ticket_account_info.realloc(space, false)?;
+            }
+            ticket_struct
+        };
+        ticket_struct.serialize(&mut &mut ticket_account_info.data.borrow_mut()[..])?;
+        raffle_struct.tickets_sold = end;
+        Ok(())
+    }
+    fn check_wallet_ticket_limit(
+        raffle_struct: &RaffleOrder,
+        ticket_struct: &RaffleTicket,
+    ) -> ProgramResult {
+        if raffle_struct.max_tickets_per_wallet != 0
+            && ticket_struct.ticket_count > raffle_struct.max_tickets_per_wallet
         {
-            let king_wallet_account_info = next_account_info(accounts)?;
-            let sys_program_info = next_account_info(accounts)?;
-            let rafflee_info = next_account_info(accounts)?;
-            let spl_accounts = &[
-                raffler_info.clone(),
-                king_wallet_account_info.clone(),
-                sys_program_info.clone(),
-                rafflee_info.clone(),
-            ];
-
-            if let Err(error) =
-                Self::handle_sol(spl_accounts, raffle_struct.owner_wallet_address, amount)
-            {
-                return Err(error);
+            return Err(MarketError::WalletTicketLimit.into());
+        }
+        Ok(())
+    }
+    // commit-reveal takes two calls from the owner once sales close: the
+    // first fixes a slot whose hash nobody knows yet, the second reveals the
+    // seed against that slot's hash. a seed never revealed fails the raffle
+    fn draw_raffle(
+        program_id: Pubkey,
+        account_info: &[AccountInfo],
+        seed: [u8; 32],
+    ) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let drawer_info = next_account_info(accounts)?; // raffle owner
+        let raffle_order_account_info = next_account_info(accounts)?;
+        let randomness_account_info = next_account_info(accounts)?; // slot hashes sysvar or oracle result
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        if *raffle_order_account_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let market_info = Self::load_market_info(&program_id, market_info_account)?;
+        let mut raffle_struct: RaffleOrder =
+            try_from_slice_unchecked(&raffle_order_account_info.data.borrow())?;
+        if !drawer_info.is_signer || *drawer_info.key != raffle_struct.owner_wallet_address {
+            return Err(MarketError::WrongOwner.into());
+        }
+        if raffle_struct.is_drawn {
+            return Err(MarketError::RaffleAlreadyDrawn.into());
+        }
+        if raffle_struct.is_cancelled {
+            return Err(MarketError::RaffleEnded.into());
+        }
+        let clock = Clock::get()?;
+        if clock.unix_timestamp as u64 <= raffle_struct.time {
+            return Err(MarketError::RaffleNotEnded.into());
+        }
+        if !Self::raffle_threshold_met(&raffle_struct) {
+            return Err(MarketError::MinTicketsNotMet.into());
+        }
+        // buyers can already be refunding by now
+        if Self::raffle_draw_missed(&raffle_struct, &clock) {
+            return Err(MarketError::DrawWindowMissed.into());
+        }
+        let provider: Box<dyn RandomnessProvider> = match raffle_struct.randomness_source {
+            RandomnessSource::CommitReveal => {
+                if raffle_struct.draw_slot == 0 {
+                    raffle_struct.draw_slot = clock
+                        .slot
+                        .checked_add(DRAW_SLOT_DELAY)
+                        .ok_or(MarketError::MathOverflow)?;
+                    raffle_struct
+                        .serialize(&mut &mut raffle_order_account_info.data.borrow_mut()[..])?;
+                    return Ok(());
+                }
+                if hashv(&[&seed]).to_bytes() != raffle_struct.seed_commitment {
+                    return Err(MarketError::SeedMismatch.into());
+                }
+                Box::new(CommitRevealProvider {
+                    seed,
+                    slot_hash: randomness::slot_hash_at(
+                        randomness_account_info,
+                        raffle_struct.draw_slot,
+                    )?,
+                })
             }
-        } else {
+            RandomnessSource::Oracle => {
+                if *randomness_account_info.key != raffle_struct.randomness_account {
+                    return Err(MarketError::ValueMisMatch.into());
+                }
+                Box::new(OracleProvider {
+                    oracle_program: market_info.randomness_oracle_program,
+                    randomness_account_info,
+                    not_before: raffle_struct.time,
+                })
+            }
+            #[cfg(feature = "local-randomness")]
+            RandomnessSource::Local => {
+                if hashv(&[&seed]).to_bytes() != raffle_struct.seed_commitment {
+                    return Err(MarketError::SeedMismatch.into());
+                }
+                Box::new(DeterministicProvider { seed })
+            }
+            #[cfg(not(feature = "local-randomness"))]
+            RandomnessSource::Local => return Err(MarketError::InvalidInstruction.into()),
+        };
+        raffle_struct.randomness = provider.randomness(raffle_order_account_info.key)?;
+        raffle_struct.is_drawn = true;
+        raffle_struct.serialize(&mut &mut raffle_order_account_info.data.borrow_mut()[..])?;
+        Ok(())
+    }
+    // maps the drawn randomness onto one distinct wallet per prize tier,
+    // weighted by tickets held. anyone may crank this with the ticket accounts
+    // holding the winning numbers (computable off-chain once drawn); tiers are
+    // settled in order and it stops at the first one whose holder is missing.
+    // a wallet that already won is re-drawn, so with fewer buyers than prizes
+    // (or after MAX_DRAW_ATTEMPTS re-draws) the lower tiers stay unawarded
+    fn settle_raffle_winners(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let raffle_order_account_info = next_account_info(accounts)?;
+        if *raffle_order_account_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut raffle_struct: RaffleOrder =
+            try_from_slice_unchecked(&raffle_order_account_info.data.borrow())?;
+        if !raffle_struct.is_drawn {
+            return Err(MarketError::RaffleNotDrawn.into());
+        }
+        let mut tickets = Vec::new();
+        for ticket_account_info in accounts {
+            // ticket accounts holding the winning numbers, in any order
+            let ticket_struct = Self::load_raffle_ticket(
+                &program_id,
+                raffle_order_account_info.key,
+                ticket_account_info,
+            )?;
+            tickets.push(ticket_struct);
+        }
+        let awarded_tiers = raffle_struct.prizes.len().min(raffle_struct.buyer_count as usize);
+        let settled_before = raffle_struct.settled_prizes as usize;
+        let mut tier = settled_before;
+        'tiers: while tier < raffle_struct.prizes.len() {
+            if tier < awarded_tiers {
+                for attempt in 0..MAX_DRAW_ATTEMPTS {
+                    let tier_randomness = hashv(&[
+                        &raffle_struct.randomness,
+                        &(tier as u64).to_le_bytes(),
+                        &attempt.to_le_bytes(),
+                    ])
+                    .to_bytes();
+                    let ticket =
+                        randomness::ticket_index(&tier_randomness, raffle_struct.tickets_sold)?;
+                    let holder = match tickets.iter().find(|ticket_struct| {
+                        ticket_struct
+                            .ranges
+                            .iter()
+                            .any(|range| range.start <= ticket && ticket < range.end)
+                    }) {
+                        Some(ticket_struct) => ticket_struct.buyer_wallet_address,
+                        None => break 'tiers,
+                    };
+                    if !raffle_struct.prizes[..tier]
+                        .iter()
+                        .any(|prize| prize.winner_wallet_address == holder)
+                    {
+                        raffle_struct.prizes[tier].winner_wallet_address = holder;
+                        break;
+                    }
+                }
+            }
+            tier += 1;
+        }
+        if tier == settled_before {
             return Err(MarketError::ValueMisMatch.into());
         }
+        raffle_struct.settled_prizes = tier as u8;
         raffle_struct.serialize(&mut &mut raffle_order_account_info.data.borrow_mut()[..])?;
         Ok(())
     }
-    fn end_raffle(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
+    fn load_raffle_ticket(
+        program_id: &Pubkey,
+        raffle_account: &Pubkey,
+        ticket_account_info: &AccountInfo,
+    ) -> Result<RaffleTicket, ProgramError> {
+        if *ticket_account_info.owner != *program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let ticket_struct: RaffleTicket =
+            try_from_slice_unchecked(&ticket_account_info.data.borrow())?;
+        let (ticket_pda, _nonce) = Pubkey::find_program_address(
+            &[
+                b"raffle_ticket",
+                raffle_account.as_ref(),
+                ticket_struct.buyer_wallet_address.as_ref(),
+            ],
+            program_id,
+        );
+        if *ticket_account_info.key != ticket_pda || !ticket_struct.is_initialized {
+            return Err(MarketError::PdaError.into());
+        }
+        Ok(ticket_struct)
+    }
+    fn end_raffle(
+        program_id: Pubkey,
+        account_info: &[AccountInfo],
+        prize_index: usize,
+    ) -> ProgramResult {
         let accounts = &mut account_info.iter();
         let king = next_account_info(accounts)?;
         let raffler_info = next_account_info(accounts)?; //get from sellerOrder Account in web3 claiming k liyey
@@ -1557,37 +2753,31 @@ impl Processor {
         }
         let raffle_struct: RaffleOrder =
             try_from_slice_unchecked(&mut raffle_order_account_info.data.borrow())?;
+        let prize = raffle_struct
+            .prizes
+            .get(prize_index)
+            .ok_or(MarketError::InvalidInstruction)?;
 
-        let mut exist = false;
-        for i in 0..raffle_struct.raffle_entry_record.len() {
-            if *raffler_info.key == raffle_struct.raffle_entry_record[i].raffler_address {
-                exist = true;
-                break;
-            }
-        }
-        if account_info.len() == 10 || account_info.len() == 11 {
+        let exist = prize_index < raffle_struct.settled_prizes as usize
+            && *raffler_info.key == prize.winner_wallet_address;
+        if account_info.len() == 11 || account_info.len() == 12 {
             let admin = next_account_info(accounts)?;
             let _system_account = next_account_info(accounts)?;
-            if *admin.key
-                == Pubkey::from_str("to_check_spl_address").unwrap()
-                && admin.is_signer == true
-            {
+            let market_info_account = next_account_info(accounts)?; // marketplace config
+            let market_info = Self::load_market_info(&program_id, market_info_account)?;
+            if *admin.key == market_info.owner_wallet_address && admin.is_signer == true {
                 if *king.key == raffle_struct.owner_wallet_address
-                    && *raffle_nft_token_account_info.key == raffle_struct.token_account
+                    && *raffle_nft_token_account_info.key == prize.token_account
                 // && king.is_signer == true
                 {
-                    if let Err(error) =
-                        Self::transfer_to_winner_raffle(program_id, account_info, exist)
-                    {
-                        return Err(error);
-                    }
+                    Self::transfer_to_winner_raffle(program_id, account_info, prize_index, exist)?;
                 } else {
                     return Err(MarketError::ValueMisMatch.into());
                 }
             } else {
                 return Err(MarketError::WrongOwner.into());
             }
-            if account_info.len() == 11 {
+            if account_info.len() == 12 {
                 let feature_account = next_account_info(accounts)?;
                 **king.try_borrow_mut_lamports()? = king
                 .lamports()
@@ -1599,13 +2789,10 @@ impl Processor {
             
         } else {
             if *king.key == raffle_struct.owner_wallet_address
-                && *raffle_nft_token_account_info.key == raffle_struct.token_account
+                && *raffle_nft_token_account_info.key == prize.token_account
                 && king.is_signer == true
             {
-                if let Err(error) = Self::transfer_to_winner_raffle(program_id, account_info, exist)
-                {
-                    return Err(error);
-                }
+                Self::transfer_to_winner_raffle(program_id, account_info, prize_index, exist)?;
             } else {
                 return Err(MarketError::ValueMisMatch.into());
             }
@@ -1626,6 +2813,7 @@ impl Processor {
     fn transfer_to_winner_raffle(
         program_id: Pubkey,
         account_info: &[AccountInfo],
+        prize_index: usize,
         exist: bool,
     ) -> ProgramResult {
         let accounts = &mut account_info.iter();
@@ -1642,9 +2830,26 @@ impl Processor {
         }
         let (pda, _nonce) =
             Pubkey::find_program_address(&[b"seeds_for_pda"], &program_id);
-        let raffle_struct: RaffleOrder =
+        let mut raffle_struct: RaffleOrder =
             try_from_slice_unchecked(&mut raffle_order_account_info.data.borrow())?;
-        if Clock::get()?.unix_timestamp as u64 > raffle_struct.time && exist == true {
+        let prize = raffle_struct
+            .prizes
+            .get(prize_index)
+            .ok_or(MarketError::InvalidInstruction)?
+            .clone();
+        if prize.is_claimed {
+            return Err(MarketError::PrizeAlreadyClaimed.into());
+        }
+        if *raffle_nft_token_account_info.key != prize.token_account {
+            return Err(MarketError::ValueMisMatch.into());
+        }
+        let clock = Clock::get()?;
+        if clock.unix_timestamp as u64 > raffle_struct.time && exist == true {
+            if SPLS::Account::unpack_unchecked(&raffle_nft_new_token_account.data.borrow())?.owner
+                != prize.winner_wallet_address
+            {
+                return Err(MarketError::WrongOwner.into());
+            }
             if let Err(error) = invoke_signed(
                 &SPLIX::transfer_checked(
                     token_program.key,
@@ -1683,15 +2888,13 @@ impl Processor {
             ) {
                 return Err(error);
             }
-            **king.try_borrow_mut_lamports()? = king
-                .lamports()
-                .checked_add(raffle_order_account_info.lamports())
-                .ok_or(ProgramError::InsufficientFunds)?;
-            **raffle_order_account_info.try_borrow_mut_lamports()? = 0;
-            *raffle_order_account_info.try_borrow_mut_data()? = &mut [];
-        } else if Clock::get()?.unix_timestamp as u64 > raffle_struct.time
-            && raffle_struct.raffle_entry_record.len() == 0
+        } else if clock.unix_timestamp as u64 > raffle_struct.time
+            && (!Self::raffle_threshold_met(&raffle_struct)
+                || Self::raffle_draw_missed(&raffle_struct, &clock)
+                || (prize_index < raffle_struct.settled_prizes as usize
+                    && prize.winner_wallet_address == Pubkey::default()))
         {
+            // failed or undrawn raffle, or more prizes than distinct entrants: the prize goes back
             let (pda, _nonce) =
                 Pubkey::find_program_address(&[b"seeds_for_pda"], &program_id);
             if let Err(error) = invoke_signed(
@@ -1712,168 +2915,598 @@ impl Processor {
             ) {
                 return Err(error);
             }
-            **king.try_borrow_mut_lamports()? = king
-                .lamports()
-                .checked_add(raffle_order_account_info.lamports())
-                .ok_or(ProgramError::InsufficientFunds)?;
-            **raffle_order_account_info.try_borrow_mut_lamports()? = 0;
-            *raffle_order_account_info.try_borrow_mut_data()? = &mut [];
+        } else if prize_index >= raffle_struct.settled_prizes as usize {
+            return Err(MarketError::RaffleNotDrawn.into());
         } else {
             return Err(MarketError::ValueMisMatch.into());
         }
-
+        Self::claim_raffle_prize(raffle_order_account_info, king, &mut raffle_struct, prize_index)
+    }
+    // marks a prize as handed out and closes the raffle once none are left
+    fn claim_raffle_prize(
+        raffle_order_account_info: &AccountInfo,
+        rent_receiver: &AccountInfo,
+        raffle_struct: &mut RaffleOrder,
+        prize_index: usize,
+    ) -> ProgramResult {
+        raffle_struct.prizes[prize_index].is_claimed = true;
+        Self::close_raffle_if_settled(raffle_order_account_info, rent_receiver, raffle_struct)
+    }
+    fn raffle_threshold_met(raffle_struct: &RaffleOrder) -> bool {
+        raffle_struct.tickets_sold > 0 && raffle_struct.tickets_sold >= raffle_struct.min_tickets
+    }
+    // whether ticket money goes back to buyers instead of the seller,
+    // a failed raffle only becomes refundable once it has ended
+    fn raffle_refundable(raffle_struct: &RaffleOrder) -> Result<bool, ProgramError> {
+        Ok(raffle_struct.is_cancelled
+            || !Self::raffle_threshold_met(raffle_struct)
+            || Self::raffle_draw_missed(raffle_struct, &Clock::get()?))
+    }
+    // the draw slot was never fixed (or the oracle never delivered) within the
+    // grace period, or its hash left the slot hashes sysvar unrevealed
+    fn raffle_draw_missed(raffle_struct: &RaffleOrder, clock: &Clock) -> bool {
+        if raffle_struct.is_drawn {
+            return false;
+        }
+        if raffle_struct.draw_slot == 0 {
+            clock.unix_timestamp as u64 > raffle_struct.time.saturating_add(DRAW_GRACE_PERIOD)
+        } else {
+            clock.slot > raffle_struct.draw_slot.saturating_add(MAX_ENTRIES as u64)
+        }
+    }
+    // the raffle account is kept until every prize is out and the ticket
+    // money is either paid to the seller or fully refunded
+    fn close_raffle_if_settled(
+        raffle_order_account_info: &AccountInfo,
+        rent_receiver: &AccountInfo,
+        raffle_struct: &RaffleOrder,
+    ) -> ProgramResult {
+        let funds_settled = if Self::raffle_refundable(raffle_struct)? {
+            raffle_struct.tickets_refunded == raffle_struct.tickets_sold
+        } else {
+            raffle_struct.proceeds_claimed
+        };
+        if funds_settled && raffle_struct.prizes.iter().all(|prize| prize.is_claimed) {
+            Self::close_program_account(raffle_order_account_info, rent_receiver)?;
+        } else {
+            raffle_struct.serialize(&mut &mut raffle_order_account_info.data.borrow_mut()[..])?;
+        }
+        Ok(())
+    }
+    // the data is zeroed first, a drained account keeps its bytes until the
+    // transaction ends and could otherwise be loaded again by a later
+    // instruction in it
+    fn close_program_account(
+        account_info: &AccountInfo,
+        rent_receiver: &AccountInfo,
+    ) -> ProgramResult {
+        account_info.try_borrow_mut_data()?.fill(0);
+        **rent_receiver.try_borrow_mut_lamports()? = rent_receiver
+            .lamports()
+            .checked_add(account_info.lamports())
+            .ok_or(ProgramError::InsufficientFunds)?;
+        **account_info.try_borrow_mut_lamports()? = 0;
+        *account_info.try_borrow_mut_data()? = &mut [];
         Ok(())
     }
+    // pays a raffle's escrowed sol out to the seller and treasury
     fn handle_sol(
+        program_id: &Pubkey,
         account_info: &[AccountInfo],
+        market_info: &BlackMarketInfo,
+        metadata: &Metadata,
         owner_wallet_address: Pubkey,
         amount: u64,
+        fee_basis_points: u16,
     ) -> ProgramResult {
         let accounts = &mut account_info.iter();
-        let raffler_info = next_account_info(accounts)?; //cat king wallet
+        let pda_account_info = next_account_info(accounts)?; // sol escrow
         let king_wallet_account_info = next_account_info(accounts)?;
         let sys_program_info = next_account_info(accounts)?;
         let rafflee_info = next_account_info(accounts)?;
 
-        if owner_wallet_address
-            == Pubkey::from_str("to_check_spl_address").unwrap()
+        let (pda, _nonce) = Pubkey::find_program_address(&[b"seeds_for_pda$0!"], program_id);
+        if *pda_account_info.key != pda {
+            return Err(MarketError::PdaError.into());
+        }
+        if *king_wallet_account_info.key != market_info.treasury_wallet_address {
+            return Err(MarketError::WrongOwner.into());
+        }
+        let (seller_amount, fee_amount) = if owner_wallet_address == market_info.owner_wallet_address
         {
-            if let Err(error) = invoke(
-                &transfer(&raffler_info.key, king_wallet_account_info.key, amount),
-                &[
-                    sys_program_info.clone(),
-                    raffler_info.clone(),
-                    king_wallet_account_info.clone(),
-                ],
-            ) {
-                return Err(error);
-            }
+            (0, amount)
         } else {
-            if *king_wallet_account_info.key
-                != Pubkey::from_str("to_check_spl_address").unwrap()
-            {
-                return Err(MarketError::WrongOwner.into());
-            }
-            if let Err(error) = invoke(
-                &transfer(
-                    &raffler_info.key,
-                    rafflee_info.key,
-                    (amount as f64 * 97.5 / 100.00) as u64,
-                ),
+            fees::split_amount(amount, fee_basis_points)?
+        };
+        if *rafflee_info.key != owner_wallet_address {
+            return Err(MarketError::WrongOwner.into());
+        }
+        let royalty =
+            fees::royalty_amount(amount, fee_amount, metadata.data.seller_fee_basis_points)?;
+        let royalty_paid = Self::pay_creator_royalties(
+            &PaymentMint::Native,
+            sys_program_info,
+            pda_account_info,
+            pda_account_info,
+            accounts,
+            metadata,
+            royalty,
+            &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
+        )?;
+        let seller_amount = seller_amount
+            .checked_sub(royalty_paid)
+            .ok_or(MarketError::MathOverflow)?;
+        if seller_amount > 0 {
+            invoke_signed(
+                &transfer(&pda, rafflee_info.key, seller_amount),
                 &[
                     sys_program_info.clone(),
-                    raffler_info.clone(),
+                    pda_account_info.clone(),
                     rafflee_info.clone(),
                 ],
-            ) {
-                return Err(error);
-            }
-
-            if let Err(error) = invoke(
-                &transfer(
-                    &raffler_info.key,
-                    king_wallet_account_info.key,
-                    (amount as f64 * 2.5 / 100.00) as u64,
-                ),
-                &[
-                    sys_program_info.clone(),
-                    raffler_info.clone(),
-                    king_wallet_account_info.clone(),
-                ],
-            ) {
-                return Err(error);
-            }
+                &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
+            )?;
         }
+        invoke_signed(
+            &transfer(&pda, king_wallet_account_info.key, fee_amount),
+            &[
+                sys_program_info.clone(),
+                pda_account_info.clone(),
+                king_wallet_account_info.clone(),
+            ],
+            &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
+        )?;
         Ok(())
     }
-
+    // the pda owns every spl escrow, auctions' and other raffles' too, so the
+    // escrow has to hold this raffle's mint
+    fn check_raffle_escrow(
+        token_type: &Pubkey,
+        pda_spl_token_account_info: &AccountInfo,
+        spl_mint_account_info: &AccountInfo,
+        pda: &Pubkey,
+    ) -> ProgramResult {
+        let pda_spl_token_account =
+            SPLS::Account::unpack_unchecked(&pda_spl_token_account_info.data.borrow())?;
+        if pda_spl_token_account.owner != *pda
+            || pda_spl_token_account.mint != *token_type
+            || *spl_mint_account_info.key != *token_type
+        {
+            return Err(MarketError::PdaError.into());
+        }
+        Ok(())
+    }
+    // pays a raffle's escrowed spl tokens out to the seller and treasury
+    #[allow(clippy::too_many_arguments)]
     fn handle_spl_tokens(
+        program_id: &Pubkey,
         account_info: &[AccountInfo],
+        market_info: &BlackMarketInfo,
+        metadata: &Metadata,
+        token_type: &Pubkey,
         owner_wallet_address: Pubkey,
         amount: u64,
+        fee_basis_points: u16,
     ) -> ProgramResult {
         let accounts = &mut account_info.iter();
-        let raffler_info = next_account_info(accounts)?; //cat king wallet
-        let raffler_spl_token_account_info = next_account_info(accounts)?;
+        let pda_account_info = next_account_info(accounts)?;
+        let pda_spl_token_account_info = next_account_info(accounts)?; // escrow
         let spl_mint_account_info = next_account_info(accounts)?;
         let king_spl_token_account = next_account_info(accounts)?;
         let token_program = next_account_info(accounts)?; // token program
         let client_spl_token_account_info = next_account_info(accounts)?; //ppublic owner
-        if SPLS::Account::unpack_unchecked(&mut king_spl_token_account.data.borrow())?.owner
-            != Pubkey::from_str("to_check_spl_address").unwrap()
-        {
-            return Err(MarketError::WrongOwner.into());
+        let (pda, _nonce) = Pubkey::find_program_address(&[b"seeds_for_pda"], program_id);
+        if *pda_account_info.key != pda {
+            return Err(MarketError::PdaError.into());
         }
-        if owner_wallet_address
-            == Pubkey::from_str("to_check_spl_address").unwrap()
+        Self::check_raffle_escrow(
+            token_type,
+            pda_spl_token_account_info,
+            spl_mint_account_info,
+            &pda,
+        )?;
+        let payment = PaymentMint::Spl(*token_type);
+        Self::check_treasury_account(&payment, king_spl_token_account, market_info)?;
+        let (seller_amount, fee_amount) = if owner_wallet_address == market_info.owner_wallet_address
         {
-            if let Err(error) = invoke(
+            (0, amount)
+        } else {
+            fees::split_amount(amount, fee_basis_points)?
+        };
+        let royalty =
+            fees::royalty_amount(amount, fee_amount, metadata.data.seller_fee_basis_points)?;
+        let royalty_paid = Self::pay_creator_royalties(
+            &payment,
+            token_program,
+            pda_spl_token_account_info,
+            pda_account_info,
+            accounts,
+            metadata,
+            royalty,
+            &[&[&b"seeds_for_pda"[..], &[_nonce]]],
+        )?;
+        let seller_amount = seller_amount
+            .checked_sub(royalty_paid)
+            .ok_or(MarketError::MathOverflow)?;
+        if seller_amount > 0 {
+            Self::check_payment_account(
+                &payment,
+                client_spl_token_account_info,
+                &owner_wallet_address,
+            )?;
+            invoke_signed(
                 &SPLIX::transfer(
                     token_program.key,
-                    raffler_spl_token_account_info.key,
-                    king_spl_token_account.key,
-                    raffler_info.key,
-                    &[raffler_info.key],
-                    amount,
+                    pda_spl_token_account_info.key,
+                    client_spl_token_account_info.key,
+                    &pda,
+                    &[&pda],
+                    seller_amount,
                 )?,
                 &[
-                    raffler_spl_token_account_info.clone(),
+                    pda_spl_token_account_info.clone(),
                     spl_mint_account_info.clone(),
-                    king_spl_token_account.clone(),
-                    raffler_info.clone(),
+                    client_spl_token_account_info.clone(),
+                    pda_account_info.clone(),
                 ],
-            ) {
-                return Err(error);
-            }
+                &[&[&b"seeds_for_pda"[..], &[_nonce]]],
+            )?;
+        }
+        invoke_signed(
+            &SPLIX::transfer(
+                token_program.key,
+                pda_spl_token_account_info.key,
+                king_spl_token_account.key,
+                &pda,
+                &[&pda],
+                fee_amount,
+            )?,
+            &[
+                pda_spl_token_account_info.clone(),
+                spl_mint_account_info.clone(),
+                king_spl_token_account.clone(),
+                pda_account_info.clone(),
+            ],
+            &[&[&b"seeds_for_pda"[..], &[_nonce]]],
+        )?;
+        Ok(())
+    }
+    // releases a successful raffle's ticket money once it has been drawn
+    fn claim_raffle_proceeds(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let king = next_account_info(accounts)?; // raffle owner
+        let raffle_order_account_info = next_account_info(accounts)?;
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        if *raffle_order_account_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let market_info = Self::load_market_info(&program_id, market_info_account)?;
+        let mut raffle_struct: RaffleOrder =
+            try_from_slice_unchecked(&raffle_order_account_info.data.borrow())?;
+        if !king.is_signer || *king.key != raffle_struct.owner_wallet_address {
+            return Err(MarketError::WrongOwner.into());
+        }
+        if !raffle_struct.is_drawn {
+            return Err(MarketError::RaffleNotDrawn.into());
+        }
+        if raffle_struct.proceeds_claimed || raffle_struct.is_cancelled {
+            return Err(MarketError::ValueMisMatch.into());
+        }
+        let amount = raffle_struct
+            .price
+            .checked_mul(raffle_struct.tickets_sold)
+            .ok_or(MarketError::MathOverflow)?;
+        let mut fee_basis_points = market_info.raffle_fee_basis_points;
+        if raffle_struct.is_featured {
+            fee_basis_points += market_info.featuring_fee_basis_points;
+        }
+        if raffle_struct.token_type
+            != Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap()
+        {
+            let pda_account_info = next_account_info(accounts)?;
+            let pda_spl_token_account_info = next_account_info(accounts)?;
+            let spl_mint_account_info = next_account_info(accounts)?;
+            let king_spl_token_account = next_account_info(accounts)?; // treasury
+            let token_program = next_account_info(accounts)?; // token program
+            let client_spl_token_account_info = next_account_info(accounts)?; // seller
+            let metadata_account = next_account_info(accounts)?; // creator token accounts follow
+            let metadata = Self::load_nft_metadata(metadata_account, &raffle_struct.nft_mint)?;
+            let mut spl_accounts = vec![
+                pda_account_info.clone(),
+                pda_spl_token_account_info.clone(),
+                spl_mint_account_info.clone(),
+                king_spl_token_account.clone(),
+                token_program.clone(),
+                client_spl_token_account_info.clone(),
+            ];
+            spl_accounts.extend(accounts.cloned());
+            Self::handle_spl_tokens(
+                &program_id,
+                &spl_accounts,
+                &market_info,
+                &metadata,
+                &raffle_struct.token_type,
+                raffle_struct.owner_wallet_address,
+                amount,
+                fee_basis_points,
+            )?;
         } else {
-            if SPLS::Account::unpack_unchecked(&mut client_spl_token_account_info.data.borrow())?
-                .owner
-                != owner_wallet_address
+            let pda_account_info = next_account_info(accounts)?;
+            let king_wallet_account_info = next_account_info(accounts)?; // treasury
+            let sys_program_info = next_account_info(accounts)?;
+            let metadata_account = next_account_info(accounts)?; // creator wallets follow
+            let metadata = Self::load_nft_metadata(metadata_account, &raffle_struct.nft_mint)?;
+            let mut sol_accounts = vec![
+                pda_account_info.clone(),
+                king_wallet_account_info.clone(),
+                sys_program_info.clone(),
+                king.clone(),
+            ];
+            sol_accounts.extend(accounts.cloned());
+            Self::handle_sol(
+                &program_id,
+                &sol_accounts,
+                &market_info,
+                &metadata,
+                raffle_struct.owner_wallet_address,
+                amount,
+                fee_basis_points,
+            )?;
+        }
+        raffle_struct.proceeds_claimed = true;
+        Self::close_raffle_if_settled(raffle_order_account_info, king, &raffle_struct)
+    }
+    // buyers of a cancelled raffle, or one that ended under its minimum, get
+    // their tickets back
+    fn refund_raffle_ticket(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let raffler_info = next_account_info(accounts)?; // ticket buyer
+        let raffle_order_account_info = next_account_info(accounts)?;
+        let ticket_account_info = next_account_info(accounts)?;
+        let king = next_account_info(accounts)?; // raffle owner, gets the rent once the raffle closes
+        if *raffle_order_account_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut raffle_struct: RaffleOrder =
+            try_from_slice_unchecked(&raffle_order_account_info.data.borrow())?;
+        if !raffler_info.is_signer {
+            return Err(MarketError::WrongOwner.into());
+        }
+        if *king.key != raffle_struct.owner_wallet_address {
+            return Err(MarketError::WrongOwner.into());
+        }
+        if !raffle_struct.is_cancelled {
+            let clock = Clock::get()?;
+            if clock.unix_timestamp as u64 <= raffle_struct.time {
+                return Err(MarketError::RaffleNotEnded.into());
+            }
+            if Self::raffle_threshold_met(&raffle_struct)
+                && !Self::raffle_draw_missed(&raffle_struct, &clock)
             {
-                return Err(MarketError::WrongOwner.into());
+                return Err(MarketError::RefundNotAvailable.into());
             }
-            if let Err(error) = invoke(
+        }
+        let escrow_accounts: Vec<AccountInfo> = accounts.cloned().collect();
+        let (refund_destination_info, escrow_accounts) = if raffle_struct.token_type
+            != Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap()
+        {
+            // pda, pda escrow token account, mint, token program, then the buyer's token account
+            let (destination, escrow) = escrow_accounts
+                .split_last()
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+            (destination.clone(), escrow.to_vec())
+        } else {
+            // sol escrow pda, system program
+            (raffler_info.clone(), escrow_accounts)
+        };
+        Self::refund_raffle_buyer(
+            &program_id,
+            raffle_order_account_info.key,
+            &mut raffle_struct,
+            ticket_account_info,
+            raffler_info,
+            &refund_destination_info,
+            &escrow_accounts,
+        )?;
+        Self::close_raffle_if_settled(raffle_order_account_info, king, &raffle_struct)
+    }
+    // pays one buyer back out of escrow and closes their ticket account
+    fn refund_raffle_buyer<'a>(
+        program_id: &Pubkey,
+        raffle_account: &Pubkey,
+        raffle_struct: &mut RaffleOrder,
+        ticket_account_info: &AccountInfo<'a>,
+        raffler_info: &AccountInfo<'a>,
+        refund_destination_info: &AccountInfo<'a>,
+        escrow_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        let ticket_struct = Self::load_raffle_ticket(program_id, raffle_account, ticket_account_info)?;
+        if *raffler_info.key != ticket_struct.buyer_wallet_address {
+            return Err(MarketError::WrongOwner.into());
+        }
+        let amount = raffle_struct
+            .price
+            .checked_mul(ticket_struct.ticket_count)
+            .ok_or(MarketError::MathOverflow)?;
+        let escrow = &mut escrow_accounts.iter();
+        if raffle_struct.token_type
+            != Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap()
+        {
+            let pda_account_info = next_account_info(escrow)?;
+            let pda_spl_token_account_info = next_account_info(escrow)?; // escrow
+            let spl_mint_account_info = next_account_info(escrow)?;
+            let token_program = next_account_info(escrow)?;
+            let (pda, _nonce) = Pubkey::find_program_address(&[b"seeds_for_pda"], program_id);
+            if *pda_account_info.key != pda {
+                return Err(MarketError::PdaError.into());
+            }
+            Self::check_raffle_escrow(
+                &raffle_struct.token_type,
+                pda_spl_token_account_info,
+                spl_mint_account_info,
+                &pda,
+            )?;
+            Self::check_payment_account(
+                &PaymentMint::Spl(raffle_struct.token_type),
+                refund_destination_info,
+                raffler_info.key,
+            )?;
+            invoke_signed(
                 &SPLIX::transfer(
                     token_program.key,
-                    raffler_spl_token_account_info.key,
-                    client_spl_token_account_info.key,
-                    raffler_info.key,
-                    &[raffler_info.key],
-                    (amount as f64 * 97.5 / 100.00) as u64,
+                    pda_spl_token_account_info.key,
+                    refund_destination_info.key,
+                    &pda,
+                    &[&pda],
+                    amount,
                 )?,
                 &[
-                    raffler_spl_token_account_info.clone(),
+                    pda_spl_token_account_info.clone(),
                     spl_mint_account_info.clone(),
-                    client_spl_token_account_info.clone(),
-                    raffler_info.clone(),
+                    refund_destination_info.clone(),
+                    pda_account_info.clone(),
                 ],
-            ) {
-                return Err(error);
+                &[&[&b"seeds_for_pda"[..], &[_nonce]]],
+            )?;
+        } else {
+            let pda_account_info = next_account_info(escrow)?; // sol escrow
+            let sys_program_info = next_account_info(escrow)?;
+            let (pda, _nonce) = Pubkey::find_program_address(&[b"seeds_for_pda$0!"], program_id);
+            if *pda_account_info.key != pda {
+                return Err(MarketError::PdaError.into());
+            }
+            invoke_signed(
+                &transfer(&pda, refund_destination_info.key, amount),
+                &[
+                    sys_program_info.clone(),
+                    pda_account_info.clone(),
+                    refund_destination_info.clone(),
+                ],
+                &[&[&b"seeds_for_pda$0!"[..], &[_nonce]]],
+            )?;
+        }
+        //closing the ticket account
+        Self::close_program_account(ticket_account_info, raffler_info)?;
+        raffle_struct.tickets_refunded = raffle_struct
+            .tickets_refunded
+            .checked_add(ticket_struct.ticket_count)
+            .ok_or(MarketError::MathOverflow)?;
+        Ok(())
+    }
+    // the owner may cancel before the raffle ends or while nothing has sold,
+    // the admin at any point before the seller is paid or a prize goes out.
+    // prizes go back to the owner here; buyers passed along are refunded
+    // straight away and the rest can reclaim through RefundRaffleTicket
+    fn cancel_raffle(program_id: Pubkey, account_info: &[AccountInfo]) -> ProgramResult {
+        let accounts = &mut account_info.iter();
+        let canceller_info = next_account_info(accounts)?; // raffle owner or admin
+        let raffle_order_account_info = next_account_info(accounts)?;
+        let king = next_account_info(accounts)?; // raffle owner
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        let pda_account_info = next_account_info(accounts)?; // holds the prize authority
+        let token_program = next_account_info(accounts)?;
+        if *raffle_order_account_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let market_info = Self::load_market_info(&program_id, market_info_account)?;
+        let mut raffle_struct: RaffleOrder =
+            try_from_slice_unchecked(&raffle_order_account_info.data.borrow())?;
+        if !canceller_info.is_signer || *king.key != raffle_struct.owner_wallet_address {
+            return Err(MarketError::WrongOwner.into());
+        }
+        if raffle_struct.is_cancelled
+            || raffle_struct.proceeds_claimed
+            || raffle_struct.prizes.iter().any(|prize| prize.is_claimed)
+        {
+            return Err(MarketError::CannotCancel.into());
+        }
+        if *canceller_info.key != market_info.owner_wallet_address {
+            if *canceller_info.key != raffle_struct.owner_wallet_address {
+                return Err(MarketError::WrongOwner.into());
             }
-            if let Err(error) = invoke(
-                &SPLIX::transfer(
+            if raffle_struct.is_drawn
+                || (Clock::get()?.unix_timestamp as u64 > raffle_struct.time
+                    && raffle_struct.tickets_sold != 0)
+            {
+                return Err(MarketError::CannotCancel.into());
+            }
+        }
+        let (pda, _nonce) =
+            Pubkey::find_program_address(&[b"seeds_for_pda"], &program_id);
+        if *pda_account_info.key != pda {
+            return Err(MarketError::PdaError.into());
+        }
+        for prize in raffle_struct.prizes.iter_mut() {
+            let prize_token_account_info = next_account_info(accounts)?; // prizes in tier order
+            if *prize_token_account_info.key != prize.token_account {
+                return Err(MarketError::ValueMisMatch.into());
+            }
+            invoke_signed(
+                &SPLIX::set_authority(
                     token_program.key,
-                    raffler_spl_token_account_info.key,
-                    king_spl_token_account.key,
-                    raffler_info.key,
-                    &[raffler_info.key],
-                    (amount as f64 * 2.5 / 100.00) as u64,
+                    prize_token_account_info.key,
+                    Some(king.key),
+                    SPLIX::AuthorityType::AccountOwner,
+                    &pda,
+                    &[&pda],
                 )?,
                 &[
-                    raffler_spl_token_account_info.clone(),
-                    spl_mint_account_info.clone(),
-                    king_spl_token_account.clone(),
-                    raffler_info.clone(),
+                    token_program.clone(),
+                    prize_token_account_info.clone(),
+                    pda_account_info.clone(),
                 ],
-            ) {
-                return Err(error);
-            }
-        }
-        Ok(())
+                &[&[&b"seeds_for_pda"[..], &[_nonce]]],
+            )?;
+            prize.is_claimed = true;
+        }
+        raffle_struct.is_cancelled = true;
+        let is_spl = raffle_struct.token_type
+            != Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+        let mut escrow_accounts = Vec::new();
+        if is_spl {
+            // pda escrow token account and mint, the pda and token program are above
+            let pda_spl_token_account_info = next_account_info(accounts)?;
+            let spl_mint_account_info = next_account_info(accounts)?;
+            Self::check_raffle_escrow(
+                &raffle_struct.token_type,
+                pda_spl_token_account_info,
+                spl_mint_account_info,
+                &pda,
+            )?;
+            escrow_accounts.push(pda_account_info.clone());
+            escrow_accounts.push(pda_spl_token_account_info.clone());
+            escrow_accounts.push(spl_mint_account_info.clone());
+            escrow_accounts.push(token_program.clone());
+        } else if raffle_struct.tickets_sold != 0 {
+            let sol_pda_account_info = next_account_info(accounts)?; // sol escrow
+            let sys_program_info = next_account_info(accounts)?;
+            escrow_accounts.push(sol_pda_account_info.clone());
+            escrow_accounts.push(sys_program_info.clone());
+        }
+        // (ticket account, buyer wallet) pairs, plus the buyer's token account for spl raffles
+        while let Some(ticket_account_info) = accounts.next() {
+            let raffler_info = next_account_info(accounts)?;
+            let refund_destination_info = if is_spl {
+                next_account_info(accounts)?
+            } else {
+                raffler_info
+            };
+            Self::refund_raffle_buyer(
+                &program_id,
+                raffle_order_account_info.key,
+                &mut raffle_struct,
+                ticket_account_info,
+                raffler_info,
+                refund_destination_info,
+                &escrow_accounts,
+            )?;
+        }
+        Self::close_raffle_if_settled(raffle_order_account_info, king, &raffle_struct)
     }
     fn handle_raffle_non_transfers(
         program_id: Pubkey,
         account_info: &[AccountInfo],
+        prize_index: usize,
     ) -> ProgramResult {
         let accounts = &mut account_info.iter();
         let king = next_account_info(accounts)?; // signer
@@ -1884,30 +3517,34 @@ impl Processor {
         let pda_account_info = next_account_info(accounts)?; //which holder the authority for NFT on Auction
         let raffle_nft_new_token_account = next_account_info(accounts)?; // new token account of user to send nft to
         let token_program = next_account_info(accounts)?;
+        let market_info_account = next_account_info(accounts)?; // marketplace config
+        let market_info = Self::load_market_info(&program_id, market_info_account)?;
         let (pda, _nonce) =
             Pubkey::find_program_address(&[b"seeds_for_pda"], &program_id);
-        let raffle_struct: RaffleOrder =
+        let mut raffle_struct: RaffleOrder =
             try_from_slice_unchecked(&mut raffle_order_account_info.data.borrow())?;
+        let prize = raffle_struct
+            .prizes
+            .get(prize_index)
+            .ok_or(MarketError::InvalidInstruction)?
+            .clone();
         if king.is_signer != true {
             return Err(MarketError::WrongOwner.into());
         }
-        if king.key
-            != &Pubkey::from_str("to_check_spl_address").unwrap()
-        {
+        if *king.key != market_info.owner_wallet_address {
             return Err(MarketError::WrongOwner.into());
         }
         // if *king.key != raffle_struct.owner_wallet_address
-        if *raffle_nft_token_account_info.key != raffle_struct.token_account {
+        if *raffle_nft_token_account_info.key != prize.token_account {
             return Err(MarketError::ValueMisMatch.into());
         }
-        let mut exist = false;
-
-        for i in 0..raffle_struct.raffle_entry_record.len() {
-            if *raffler_info.key == raffle_struct.raffle_entry_record[i].raffler_address {
-                exist = true;
-                break;
-            }
+        if prize_index >= raffle_struct.settled_prizes as usize {
+            return Err(MarketError::RaffleNotDrawn.into());
+        }
+        if prize.is_claimed {
+            return Err(MarketError::PrizeAlreadyClaimed.into());
         }
+        let exist = *raffler_info.key == prize.winner_wallet_address;
         if exist == true
             && SPLS::Account::unpack_unchecked(&mut raffle_nft_new_token_account.data.borrow())?
                 .owner
@@ -1952,12 +3589,12 @@ impl Processor {
             ) {
                 return Err(error);
             }
-            **king.try_borrow_mut_lamports()? = king
-                .lamports()
-                .checked_add(raffle_order_account_info.lamports())
-                .ok_or(ProgramError::InsufficientFunds)?;
-            **raffle_order_account_info.try_borrow_mut_lamports()? = 0;
-            *raffle_order_account_info.try_borrow_mut_data()? = &mut [];
+            Self::claim_raffle_prize(
+                raffle_order_account_info,
+                king,
+                &mut raffle_struct,
+                prize_index,
+            )?;
         }
         Ok(())
     }
@@ -0,0 +1,117 @@
+//! Randomness sources a raffle can be drawn from.
+//!
+//! `draw_raffle` only asks a provider for 32 bytes bound to the raffle
+//! account; how those bytes are produced (and verified) lives here, so a new
+//! vendor only needs a new `RandomnessProvider` implementation.
+use {
+    crate::{error::MarketError, state::OracleRandomness},
+    borsh::BorshDeserialize,
+    solana_program::{
+        account_info::AccountInfo, hash::hashv, program_error::ProgramError, pubkey::Pubkey,
+        sysvar::slot_hashes,
+    },
+};
+
+pub trait RandomnessProvider {
+    fn randomness(&self, raffle_account: &Pubkey) -> Result<[u8; 32], ProgramError>;
+}
+
+/// Seed revealed by the raffle owner mixed with the hash of a slot fixed
+/// after sales closed, which nobody knew when the seed was committed or the
+/// slot was fixed.
+/// The caller is responsible for checking the seed against the commitment.
+pub struct CommitRevealProvider {
+    pub seed: [u8; 32],
+    pub slot_hash: [u8; 32],
+}
+
+impl RandomnessProvider for CommitRevealProvider {
+    fn randomness(&self, raffle_account: &Pubkey) -> Result<[u8; 32], ProgramError> {
+        Ok(hashv(&[&self.seed, &self.slot_hash, raffle_account.as_ref()]).to_bytes())
+    }
+}
+
+/// Result account written by a VRF-style oracle program. The raffle owner
+/// requests it from the oracle before listing and the raffle records its key.
+pub struct OracleProvider<'a, 'b> {
+    pub oracle_program: Pubkey,
+    pub randomness_account_info: &'a AccountInfo<'b>,
+    // randomness fulfilled before this time could have been seen by buyers
+    pub not_before: u64,
+}
+
+impl RandomnessProvider for OracleProvider<'_, '_> {
+    fn randomness(&self, raffle_account: &Pubkey) -> Result<[u8; 32], ProgramError> {
+        if self.oracle_program == Pubkey::default()
+            || *self.randomness_account_info.owner != self.oracle_program
+        {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let result: OracleRandomness =
+            BorshDeserialize::deserialize(&mut &self.randomness_account_info.data.borrow()[..])?;
+        if result.requester != *raffle_account {
+            return Err(MarketError::ValueMisMatch.into());
+        }
+        if !result.is_fulfilled || result.fulfilled_at <= self.not_before {
+            return Err(MarketError::RandomnessNotReady.into());
+        }
+        Ok(hashv(&[&result.randomness, raffle_account.as_ref()]).to_bytes())
+    }
+}
+
+/// Deterministic stand-in for local validators and tests, never compiled
+/// into a production build. The caller is responsible for checking the seed
+/// against the commitment.
+#[cfg(feature = "local-randomness")]
+pub struct DeterministicProvider {
+    pub seed: [u8; 32],
+}
+
+#[cfg(feature = "local-randomness")]
+impl RandomnessProvider for DeterministicProvider {
+    fn randomness(&self, raffle_account: &Pubkey) -> Result<[u8; 32], ProgramError> {
+        Ok(hashv(&[&self.seed, raffle_account.as_ref()]).to_bytes())
+    }
+}
+
+/// Maps randomness onto a ticket number in `0..total_tickets`.
+pub fn ticket_index(randomness: &[u8; 32], total_tickets: u64) -> Result<u64, ProgramError> {
+    if total_tickets == 0 {
+        return Err(MarketError::InvalidInstruction.into());
+    }
+    let mut ticket_bytes = [0u8; 8];
+    ticket_bytes.copy_from_slice(&randomness[..8]);
+    Ok(u64::from_le_bytes(ticket_bytes) % total_tickets)
+}
+
+/// Hash of the first slot produced at or after `slot`, read from the slot
+/// hashes sysvar. Skipped slots have no entry, so the next produced one
+/// stands in; the answer is fixed as long as `slot` is still in the sysvar.
+pub fn slot_hash_at(slot_hashes_info: &AccountInfo, slot: u64) -> Result<[u8; 32], ProgramError> {
+    if *slot_hashes_info.key != slot_hashes::id() {
+        return Err(ProgramError::InvalidArgument);
+    }
+    // too large to deserialize in-program; the layout is a u64 entry
+    // count followed by (slot: u64, hash: [u8; 32]) pairs, newest first
+    let data = slot_hashes_info.data.borrow();
+    if data.len() < 8 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mut count = [0u8; 8];
+    count.copy_from_slice(&data[..8]);
+    let mut found = None;
+    for index in 0..u64::from_le_bytes(count) as usize {
+        let entry = data
+            .get(8 + index * 40..8 + index * 40 + 40)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        let mut entry_slot = [0u8; 8];
+        entry_slot.copy_from_slice(&entry[..8]);
+        if u64::from_le_bytes(entry_slot) < slot {
+            break;
+        }
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&entry[8..]);
+        found = Some(hash);
+    }
+    found.ok_or_else(|| MarketError::RandomnessNotReady.into())
+}
@@ -1,6 +1,47 @@
 use borsh::BorshDeserialize;
 
 use {borsh::BorshSerialize, solana_program::pubkey::Pubkey};
+// soft close: a bid within `window` seconds of the end pushes it back by
+// `length`, up to `max_total` seconds overall (0 means no cap)
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub struct AuctionExtension {
+    pub window: u64,
+    pub length: u64,
+    pub max_total: u64,
+}
+pub const MAX_INCREMENT_TIERS: usize = 4;
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum IncrementKind {
+    Percent,
+    Absolute,
+    Tiered,
+}
+// from `threshold` upwards a bid has to beat the current one by `step`
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct IncrementTier {
+    pub threshold: u64,
+    pub step: u64,
+}
+// fixed size so the auction account layout doesn't depend on the rule,
+// only the fields for `kind` are used and the rest stay zero
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub struct BidIncrement {
+    pub kind: IncrementKind,
+    pub basis_points: u16,
+    pub step: u64,
+    pub tier_count: u8,
+    pub tiers: [IncrementTier; MAX_INCREMENT_TIERS],
+}
+// what an auction is paid in, wrapped sol at listing means native sol
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum PaymentMint {
+    Native,
+    Spl(Pubkey),
+}
+// english auction, sized for `PaymentMint::Spl` so either payment fits
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
 pub struct AuctionOrder {
@@ -10,29 +51,58 @@ pub struct AuctionOrder {
     pub time: u64,
     pub minimum_price: u64,
     pub bidder_wallet_address: Pubkey,
-    pub bidder_spl_token_account: Pubkey,
+    // the top bidder's wallet for sol, their token account for spl
+    pub bidder_payment_account: Pubkey,
     pub bid: u64,
+    // no longer kept up to date, left in place for the account layout
     pub total_bid_amount: u64,
-    pub token_type: Pubkey,
+    pub payment: PaymentMint,
+    // 0 when the listing has no buy-now option
+    pub buy_now_price: u64,
+    // hashv(reserve as u64 le bytes, salt), all zero when there is no reserve
+    pub reserve_commitment: [u8; 32],
+    pub is_reserve_revealed: bool,
+    pub reserve_price: u64,
+    pub extension: AuctionExtension,
+    pub extended_by: u64,
+    pub increment: BidIncrement,
+    // ceiling the leading bidder escrowed, the program outbids others for
+    // them up to it; never below `bid` once there is one
+    pub proxy_max: u64,
+    // bids open at this time, the nft is escrowed from listing
+    pub start_time: u64,
 }
+
+// tickets are numbered in purchase order, `end` is exclusive
 #[repr(C)]
-#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
-pub struct AuctionOrderSol {
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct TicketRange {
+    pub start: u64,
+    pub end: u64,
+}
+// one per (raffle, buyer), PDA from [b"raffle_ticket", raffle, buyer]
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct RaffleTicket {
     pub is_initialized: bool,
-    pub owner_wallet_address: Pubkey,
-    pub minimum_price: u64,
-    pub time: u64,
-    pub token_account: Pubkey,
-    pub bidder_wallet_address: Pubkey,
-    pub bid: u64,
-    pub total_bid_amount: u64,
+    pub raffle_account: Pubkey,
+    pub buyer_wallet_address: Pubkey,
+    pub ticket_count: u64,
+    pub ranges: Vec<TicketRange>,
 }
-
+// prizes are kept in tier order, the first one goes to the first drawn winner
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
-pub struct RaffleCounter {
-    pub raffler_address: Pubkey,
-    pub entry_counter: u32,
+pub struct RafflePrize {
+    pub token_account: Pubkey,
+    pub winner_wallet_address: Pubkey,
+    pub is_claimed: bool,
+}
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum RandomnessSource {
+    CommitReveal,
+    Oracle,
+    Local,
 }
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
@@ -40,11 +110,33 @@ pub struct RaffleOrder {
     pub is_initialized: bool,
     pub owner_wallet_address: Pubkey,
     pub time: u64,
-    pub token_account: Pubkey,
     pub price: u64,
     pub token_type: Pubkey,
     pub ticket_supply: u64,
-    pub raffle_entry_record: Vec<RaffleCounter>,
+    pub is_featured: bool,
+    pub seed_commitment: [u8; 32],
+    pub randomness_source: RandomnessSource,
+    pub randomness_account: Pubkey,
+    pub is_drawn: bool,
+    pub randomness: [u8; 32],
+    // commit-reveal draws from this slot's hash, fixed by the owner after
+    // sales close, 0 until then
+    pub draw_slot: u64,
+    pub tickets_sold: u64,
+    pub buyer_count: u64,
+    // below this many tickets sold the raffle fails and buyers are refunded
+    pub min_tickets: u64,
+    // 0 means a wallet may buy the whole supply
+    pub max_tickets_per_wallet: u64,
+    pub tickets_refunded: u64,
+    pub proceeds_claimed: bool,
+    pub is_cancelled: bool,
+    // prizes before this index have their winner settled
+    pub settled_prizes: u8,
+    // first prize's mint, its creators are paid royalties out of the proceeds;
+    // every other prize shares its verified collection
+    pub nft_mint: Pubkey,
+    pub prizes: Vec<RafflePrize>,
 }
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
@@ -54,11 +146,98 @@ pub struct FeaturedRaffles {
     pub is_featured: bool,
 }
 
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum DecaySchedule {
+    Linear,
+    Stepwise,
+}
+// token_type is the payment mint, wrapped sol means native sol
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct DutchAuctionOrder {
+    pub is_initialized: bool,
+    pub owner_wallet_address: Pubkey,
+    pub token_account: Pubkey,
+    pub token_type: Pubkey,
+    pub start_price: u64,
+    pub floor_price: u64,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub decay: DecaySchedule,
+    pub step_interval: u64,
+}
+
+// sol only, bids are committed until bid_end_time and revealed until reveal_end_time
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct SealedAuctionOrder {
+    pub is_initialized: bool,
+    pub owner_wallet_address: Pubkey,
+    pub token_account: Pubkey,
+    pub minimum_price: u64,
+    pub bid_end_time: u64,
+    pub reveal_end_time: u64,
+    // vickrey listing, the winner pays the second highest valid bid
+    pub is_second_price: bool,
+    pub bidder_wallet_address: Pubkey,
+    pub bid: u64,
+    pub second_bid: u64,
+    pub bid_count: u64,
+    pub bids_settled: u64,
+    pub is_settled: bool,
+}
+// PDA from [b"sealed_bid", auction, bidder], the deposit sits in the sol escrow.
+// commitment is hashv(bid as u64 le bytes, salt, bidder wallet)
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct SealedBid {
+    pub is_initialized: bool,
+    pub auction_account: Pubkey,
+    pub bidder_wallet_address: Pubkey,
+    pub commitment: [u8; 32],
+    pub deposit: u64,
+    pub is_revealed: bool,
+    pub revealed_bid: u64,
+}
+
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
 pub struct BlackMarketInfo {
     pub is_initialized: bool,
     pub owner_wallet_address: Pubkey,
-    pub raffle_fee: u64,
-    pub featuring_fee: u64,
+    pub treasury_wallet_address: Pubkey,
+    pub spl_treasury_wallet_address: Pubkey,
+    pub auction_fee_basis_points: u16,
+    pub raffle_fee_basis_points: u16,
+    pub featuring_fee_basis_points: u16,
+    pub randomness_oracle_program: Pubkey,
+    // used by auctions listed without their own extension settings
+    pub default_extension: AuctionExtension,
+    // bounds on an auction's length in seconds, from bidding open to close
+    pub min_auction_duration: u64,
+    pub max_auction_duration: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum AllowlistKind {
+    Collection,
+    Creator,
+}
+// a collection mint or creator address the marketplace lists, PDA from
+// [b"allowlist", address]
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct AllowlistEntry {
+    pub is_initialized: bool,
+    pub kind: AllowlistKind,
+    pub address: Pubkey,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct OracleRandomness {
+    pub is_fulfilled: bool,
+    pub requester: Pubkey,
+    pub fulfilled_at: u64,
+    pub randomness: [u8; 32],
 }
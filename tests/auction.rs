@@ -0,0 +1,392 @@
+mod common;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use common::{market_info, nft, pda, program_id, setup, token_account, Ledger, NOW};
+use marketplace::{
+    error::MarketError,
+    fees,
+    state::{
+        AuctionExtension, AuctionOrder, BlackMarketInfo, PaymentMint, SealedAuctionOrder, SealedBid,
+    },
+};
+use solana_program::{
+    borsh::try_from_slice_unchecked, hash::hashv, pubkey::Pubkey, system_program,
+};
+
+const SALT: [u8; 32] = [7; 32];
+const BID_RENT: u64 = 1_000;
+// seller_fee_basis_points of the nft from `common::nft`
+const ROYALTY_BASIS_POINTS: u16 = 500;
+
+// a listed nft and everything settling it touches
+struct Listing {
+    holder: Pubkey,
+    auction: Pubkey,
+    token: Pubkey,
+    mint: Pubkey,
+    metadata: Pubkey,
+    creator: Pubkey,
+    config: Pubkey,
+    treasury: Pubkey,
+    escrow: Pubkey,
+}
+
+fn listing(ledger: &mut Ledger) -> Listing {
+    let (holder, auction, creator) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let escrow = pda(&[b"seeds_for_pda$0!"]);
+    let (token, mint, metadata) = nft(ledger, escrow, Pubkey::new_unique(), creator);
+    let config = pda(&[b"black_market_info"]);
+    let config_data = market_info(Pubkey::new_unique());
+    let treasury = BlackMarketInfo::try_from_slice(&config_data)
+        .unwrap()
+        .treasury_wallet_address;
+    ledger.wallet(holder, 0);
+    ledger.wallet(creator, 0);
+    ledger.wallet(treasury, 0);
+    ledger.wallet(escrow, 0);
+    ledger.wallet(system_program::id(), 0);
+    ledger.add(spl_token::id(), Pubkey::default(), 0, vec![]);
+    ledger.add(config, program_id(), 1, config_data);
+    Listing {
+        holder,
+        auction,
+        token,
+        mint,
+        metadata,
+        creator,
+        config,
+        treasury,
+        escrow,
+    }
+}
+
+// what the seller, treasury and creator get out of a sale at `price`
+fn sale_split(price: u64) -> (u64, u64, u64) {
+    let (seller_amount, fee) = fees::split_amount(price, 250).unwrap();
+    let royalty = fees::royalty_amount(price, fee, ROYALTY_BASIS_POINTS).unwrap();
+    (seller_amount - royalty, fee, royalty)
+}
+
+// a sol english auction an hour from its end, `leader` is ahead at `bid`
+// with `proxy_max` escrowed
+fn english_auction(
+    ledger: &mut Ledger,
+    listing: &Listing,
+    leader: Pubkey,
+    bid: u64,
+    proxy_max: u64,
+) -> AuctionOrder {
+    let auction_struct = AuctionOrder {
+        is_initialized: true,
+        owner_wallet_address: listing.holder,
+        token_account: listing.token,
+        time: NOW as u64 + 3600,
+        minimum_price: 100,
+        bidder_wallet_address: leader,
+        bidder_payment_account: leader,
+        bid,
+        total_bid_amount: 0,
+        payment: PaymentMint::Native,
+        buy_now_price: 0,
+        reserve_commitment: [0; 32],
+        is_reserve_revealed: false,
+        reserve_price: 0,
+        extension: AuctionExtension {
+            window: 0,
+            length: 0,
+            max_total: 0,
+        },
+        extended_by: 0,
+        increment: fees::default_increment(),
+        proxy_max,
+        start_time: NOW as u64 - 3600,
+    };
+    ledger.wallet(leader, 0);
+    ledger.wallet(listing.escrow, proxy_max);
+    auction_struct
+}
+
+fn save_auction(ledger: &mut Ledger, listing: &Listing, auction_struct: &AuctionOrder) {
+    let mut data = auction_struct.try_to_vec().unwrap();
+    data.resize(1024, 0);
+    ledger.add(listing.auction, program_id(), 1, data);
+}
+
+// bidding has closed and the reveal window is open
+fn sealed_auction(ledger: &mut Ledger, listing: &Listing, is_second_price: bool) {
+    let auction_struct = SealedAuctionOrder {
+        is_initialized: true,
+        owner_wallet_address: listing.holder,
+        token_account: listing.token,
+        minimum_price: 100,
+        bid_end_time: NOW as u64 - 60,
+        reveal_end_time: NOW as u64 + 60,
+        is_second_price,
+        bidder_wallet_address: Pubkey::default(),
+        bid: 0,
+        second_bid: 0,
+        bid_count: 0,
+        bids_settled: 0,
+        is_settled: false,
+    };
+    ledger.add(
+        listing.auction,
+        program_id(),
+        1,
+        auction_struct.try_to_vec().unwrap(),
+    );
+}
+
+fn update_sealed_auction(
+    ledger: &mut Ledger,
+    auction: &Pubkey,
+    update: impl FnOnce(&mut SealedAuctionOrder),
+) {
+    let mut auction_struct = SealedAuctionOrder::try_from_slice(ledger.data(auction)).unwrap();
+    update(&mut auction_struct);
+    *ledger.data_mut(auction) = auction_struct.try_to_vec().unwrap();
+}
+
+// a bid placed before bidding closed, its deposit already in the escrow.
+// returns (bidder, bid account)
+fn sealed_bid(ledger: &mut Ledger, listing: &Listing, bid: u64, deposit: u64) -> (Pubkey, Pubkey) {
+    let bidder = Pubkey::new_unique();
+    let bid_account = pda(&[b"sealed_bid", listing.auction.as_ref(), bidder.as_ref()]);
+    let bid_struct = SealedBid {
+        is_initialized: true,
+        auction_account: listing.auction,
+        bidder_wallet_address: bidder,
+        commitment: hashv(&[&bid.to_le_bytes(), &SALT, bidder.as_ref()]).to_bytes(),
+        deposit,
+        is_revealed: false,
+        revealed_bid: 0,
+    };
+    ledger.wallet(bidder, 0);
+    ledger.add(
+        bid_account,
+        program_id(),
+        BID_RENT,
+        bid_struct.try_to_vec().unwrap(),
+    );
+    let escrowed = ledger.lamports(&listing.escrow) + deposit;
+    ledger.wallet(listing.escrow, escrowed);
+    update_sealed_auction(ledger, &listing.auction, |auction| auction.bid_count += 1);
+    (bidder, bid_account)
+}
+
+fn reveal(ledger: &mut Ledger, listing: &Listing, bidder: Pubkey, bid_account: Pubkey, bid: u64) {
+    let accounts = [
+        (bidder, true),
+        (listing.auction, false),
+        (bid_account, false),
+    ];
+    let data = [&[46][..], &bid.to_le_bytes(), &SALT].concat();
+    ledger.process(&accounts, &data).unwrap();
+}
+
+// CompleteSealedAuction accounts, `bids` are (bid account, bidder) pairs to settle
+fn complete_accounts(
+    listing: &Listing,
+    winner_token: Pubkey,
+    bids: &[(Pubkey, Pubkey)],
+) -> Vec<(Pubkey, bool)> {
+    let mut accounts = vec![
+        (listing.holder, false),
+        (listing.auction, false),
+        (listing.token, false),
+        (listing.mint, false),
+        (winner_token, false),
+        (listing.escrow, false),
+        (system_program::id(), false),
+        (spl_token::id(), false),
+        (listing.treasury, false),
+        (listing.config, false),
+        (listing.metadata, false),
+        (listing.creator, false),
+    ];
+    for (bid_account, bidder) in bids {
+        accounts.push((*bid_account, false));
+        accounts.push((*bidder, false));
+    }
+    accounts
+}
+
+#[test]
+fn second_price_winner_gets_back_their_deposit_over_the_clearing_price() {
+    setup();
+    let mut ledger = Ledger::default();
+    let listing = listing(&mut ledger);
+    sealed_auction(&mut ledger, &listing, true);
+    let (winner, winner_bid) = sealed_bid(&mut ledger, &listing, 800, 1_000);
+    let (runner_up, runner_up_bid) = sealed_bid(&mut ledger, &listing, 500, 600);
+    reveal(&mut ledger, &listing, winner, winner_bid, 800);
+    reveal(&mut ledger, &listing, runner_up, runner_up_bid, 500);
+    update_sealed_auction(&mut ledger, &listing.auction, |auction| {
+        auction.reveal_end_time = NOW as u64 - 1
+    });
+    let winner_token = token_account(&mut ledger, listing.mint, winner, 0);
+
+    let accounts = complete_accounts(
+        &listing,
+        winner_token,
+        &[(winner_bid, winner), (runner_up_bid, runner_up)],
+    );
+    ledger.process(&accounts, &[47]).unwrap();
+    // the winner pays the runner up's 500, not their own 800
+    let (seller_amount, fee, royalty) = sale_split(500);
+    assert_eq!(ledger.lamports(&winner), 1_000 - 500 + BID_RENT);
+    assert_eq!(ledger.lamports(&runner_up), 600 + BID_RENT);
+    assert_eq!(ledger.lamports(&listing.holder), seller_amount + 1);
+    assert_eq!(ledger.lamports(&listing.treasury), fee);
+    assert_eq!(ledger.lamports(&listing.creator), royalty);
+    assert_eq!(ledger.lamports(&listing.escrow), 0);
+    assert_eq!(ledger.lamports(&listing.auction), 0);
+}
+
+#[test]
+fn unrevealed_sealed_bid_forfeits_its_deposit_to_the_seller() {
+    setup();
+    let mut ledger = Ledger::default();
+    let listing = listing(&mut ledger);
+    sealed_auction(&mut ledger, &listing, false);
+    let (winner, winner_bid) = sealed_bid(&mut ledger, &listing, 800, 1_000);
+    let (silent, silent_bid) = sealed_bid(&mut ledger, &listing, 900, 900);
+    reveal(&mut ledger, &listing, winner, winner_bid, 800);
+    update_sealed_auction(&mut ledger, &listing.auction, |auction| {
+        auction.reveal_end_time = NOW as u64 - 1
+    });
+    let winner_token = token_account(&mut ledger, listing.mint, winner, 0);
+
+    // the unrevealed bid is left for ReclaimSealedBid
+    let accounts = complete_accounts(&listing, winner_token, &[(winner_bid, winner)]);
+    ledger.process(&accounts, &[47]).unwrap();
+    let (seller_amount, _, _) = sale_split(800);
+    assert_eq!(ledger.lamports(&winner), 1_000 - 800 + BID_RENT);
+    assert_eq!(ledger.lamports(&listing.holder), seller_amount);
+    assert_eq!(ledger.lamports(&listing.escrow), 900);
+    assert_eq!(ledger.lamports(&listing.auction), 1);
+
+    let reclaim = [
+        (silent, true),
+        (listing.auction, false),
+        (silent_bid, false),
+        (listing.holder, false),
+        (listing.escrow, false),
+        (system_program::id(), false),
+    ];
+    ledger.process(&reclaim, &[48]).unwrap();
+    // only the bid account's rent goes back to the bidder
+    assert_eq!(ledger.lamports(&silent), BID_RENT);
+    assert_eq!(ledger.lamports(&listing.holder), seller_amount + 900 + 1);
+    assert_eq!(ledger.lamports(&listing.escrow), 0);
+    assert_eq!(ledger.lamports(&listing.auction), 0);
+}
+
+#[test]
+fn buy_now_refunds_the_leaders_whole_proxy_ceiling() {
+    setup();
+    let mut ledger = Ledger::default();
+    let listing = listing(&mut ledger);
+    let (leader, buyer) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut auction_struct = english_auction(&mut ledger, &listing, leader, 300, 700);
+    auction_struct.buy_now_price = 1_000;
+    save_auction(&mut ledger, &listing, &auction_struct);
+    ledger.wallet(buyer, 1_000);
+    let buyer_token = token_account(&mut ledger, listing.mint, buyer, 0);
+
+    // sol is paid from and to wallets, and the escrow is the pda itself
+    let accounts = [
+        (buyer, true),
+        (buyer, true),
+        (listing.holder, false),
+        (listing.holder, false),
+        (listing.auction, false),
+        (listing.token, false),
+        (listing.mint, false),
+        (buyer_token, false),
+        (listing.escrow, false),
+        (listing.escrow, false),
+        (leader, false),
+        (spl_token::id(), false),
+        (system_program::id(), false),
+        (listing.treasury, false),
+        (listing.config, false),
+        (listing.metadata, false),
+        (listing.creator, false),
+    ];
+    ledger.process(&accounts, &[49]).unwrap();
+    // the leader gets back all they escrowed, not just their current bid
+    let (seller_amount, fee, royalty) = sale_split(1_000);
+    assert_eq!(ledger.lamports(&leader), 700);
+    assert_eq!(ledger.lamports(&buyer), 0);
+    assert_eq!(ledger.lamports(&listing.holder), seller_amount + 1);
+    assert_eq!(ledger.lamports(&listing.treasury), fee);
+    assert_eq!(ledger.lamports(&listing.creator), royalty);
+    assert_eq!(ledger.lamports(&listing.escrow), 0);
+    assert_eq!(ledger.lamports(&listing.auction), 0);
+}
+
+#[test]
+fn seller_can_decline_a_revealed_reserve_the_bids_fell_short_of() {
+    setup();
+    let mut ledger = Ledger::default();
+    let listing = listing(&mut ledger);
+    let leader = Pubkey::new_unique();
+    let mut auction_struct = english_auction(&mut ledger, &listing, leader, 300, 400);
+    auction_struct.time = NOW as u64 - 1;
+    auction_struct.reserve_commitment = hashv(&[&500u64.to_le_bytes(), &SALT]).to_bytes();
+    save_auction(&mut ledger, &listing, &auction_struct);
+    let leader_token = token_account(&mut ledger, listing.mint, leader, 0);
+
+    let reveal = [(listing.holder, true), (listing.auction, false)];
+    let data = [&[51][..], &500u64.to_le_bytes(), &SALT].concat();
+    ledger.process(&reveal, &data).unwrap();
+    // the proxy ceiling is short of the reserve too, so the bid stays put
+    let auction_struct: AuctionOrder =
+        try_from_slice_unchecked(ledger.data(&listing.auction)).unwrap();
+    assert!(auction_struct.is_reserve_revealed);
+    assert_eq!(auction_struct.bid, 300);
+
+    let settle = [
+        (listing.holder, false),
+        (listing.holder, false),
+        (leader, true),
+        (leader, true),
+        (listing.auction, false),
+        (listing.token, false),
+        (listing.mint, false),
+        (leader_token, false),
+        (listing.escrow, false),
+        (listing.escrow, false),
+        (spl_token::id(), false),
+        (system_program::id(), false),
+        (listing.treasury, false),
+        (listing.config, false),
+        (listing.metadata, false),
+        (listing.creator, false),
+    ];
+    assert_eq!(
+        ledger.process(&settle, &[29]),
+        Err(MarketError::ReserveNotMet.into())
+    );
+
+    let cancel = [
+        (listing.holder, true),
+        (listing.token, false),
+        (listing.auction, false),
+        (spl_token::id(), false),
+        (listing.escrow, false),
+        (listing.escrow, false),
+        (leader, false),
+        (system_program::id(), false),
+    ];
+    ledger.process(&cancel, &[7]).unwrap();
+    assert_eq!(ledger.lamports(&leader), 400);
+    assert_eq!(ledger.lamports(&listing.escrow), 0);
+    assert_eq!(ledger.lamports(&listing.holder), 1);
+    assert_eq!(ledger.lamports(&listing.auction), 0);
+}
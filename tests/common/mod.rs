@@ -0,0 +1,271 @@
+// shared by the integration tests that run instructions through the processor
+#![allow(dead_code)]
+
+use borsh::BorshSerialize;
+use marketplace::{
+    processor::Processor,
+    state::{AuctionExtension, BlackMarketInfo},
+};
+use mpl_token_metadata::{
+    pda::find_metadata_account,
+    state::{Collection, Creator, Data, Key, Metadata, MAX_METADATA_LEN},
+};
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::{ProgramResult, SUCCESS},
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    program_stubs::{self, SyscallStubs},
+    program_utils::limited_deserialize,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::SystemInstruction,
+    system_program,
+};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+use std::{collections::HashMap, str::FromStr, sync::Once};
+
+pub const NOW: i64 = 1_700_000_000;
+pub const SLOT: u64 = 5_000;
+
+pub fn program_id() -> Pubkey {
+    Pubkey::from_str("B1ackMarket111111111111111111111111111111111").unwrap()
+}
+
+// wrapped sol as a payment mint means native sol
+pub fn wsol() -> Pubkey {
+    Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap()
+}
+
+// clock and rent sysvars, and cpis that only move lamports for system
+// transfers; every other program is assumed to succeed
+pub struct Stubs;
+impl SyscallStubs for Stubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            slot: SLOT,
+            unix_timestamp: NOW,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        SUCCESS
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        if instruction.program_id != system_program::id() {
+            return Ok(());
+        }
+        if let Ok(SystemInstruction::Transfer { lamports }) =
+            limited_deserialize(&instruction.data, 1024)
+        {
+            let find = |key: &Pubkey| {
+                account_infos
+                    .iter()
+                    .find(|info| info.key == key)
+                    .ok_or(ProgramError::NotEnoughAccountKeys)
+            };
+            let from = find(&instruction.accounts[0].pubkey)?;
+            let to = find(&instruction.accounts[1].pubkey)?;
+            let remaining = from
+                .lamports()
+                .checked_sub(lamports)
+                .ok_or(ProgramError::InsufficientFunds)?;
+            **from.try_borrow_mut_lamports()? = remaining;
+            **to.try_borrow_mut_lamports()? += lamports;
+        }
+        Ok(())
+    }
+}
+
+pub fn setup() {
+    static STUBS: Once = Once::new();
+    STUBS.call_once(|| {
+        program_stubs::set_syscall_stubs(Box::new(Stubs));
+    });
+}
+
+pub struct TestAccount {
+    key: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+    data: Vec<u8>,
+}
+
+// accounts persist between instructions the way they do inside a transaction
+#[derive(Default)]
+pub struct Ledger(HashMap<Pubkey, TestAccount>);
+impl Ledger {
+    pub fn add(&mut self, key: Pubkey, owner: Pubkey, lamports: u64, data: Vec<u8>) {
+        let account = TestAccount {
+            key,
+            owner,
+            lamports,
+            data,
+        };
+        self.0.insert(key, account);
+    }
+    pub fn wallet(&mut self, key: Pubkey, lamports: u64) {
+        self.add(key, system_program::id(), lamports, vec![]);
+    }
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.0[key].lamports
+    }
+    pub fn data(&self, key: &Pubkey) -> &[u8] {
+        &self.0[key].data
+    }
+    pub fn data_mut(&mut self, key: &Pubkey) -> &mut Vec<u8> {
+        &mut self.0.get_mut(key).unwrap().data
+    }
+    // `metas` are (key, is_signer) in instruction order, a key passed more
+    // than once shares one account the way it does in the runtime
+    pub fn process(&mut self, metas: &[(Pubkey, bool)], data: &[u8]) -> ProgramResult {
+        let mut keys: Vec<Pubkey> = vec![];
+        for (key, _) in metas {
+            if !keys.contains(key) {
+                keys.push(*key);
+            }
+        }
+        let mut accounts: Vec<TestAccount> = keys
+            .iter()
+            .map(|key| self.0.remove(key).expect("unknown account"))
+            .collect();
+        let result = {
+            let shared: Vec<AccountInfo> = accounts
+                .iter_mut()
+                .map(|account| {
+                    AccountInfo::new(
+                        &account.key,
+                        false,
+                        true,
+                        &mut account.lamports,
+                        &mut account.data,
+                        &account.owner,
+                        false,
+                        0,
+                    )
+                })
+                .collect();
+            let infos: Vec<AccountInfo> = metas
+                .iter()
+                .map(|(key, is_signer)| {
+                    let mut info = shared[keys.iter().position(|k| k == key).unwrap()].clone();
+                    info.is_signer = *is_signer;
+                    info
+                })
+                .collect();
+            Processor::start_process(program_id(), &infos, data)
+        };
+        for account in accounts {
+            self.0.insert(account.key, account);
+        }
+        result
+    }
+}
+
+pub fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &program_id()).0
+}
+
+pub fn market_info(admin: Pubkey) -> Vec<u8> {
+    BlackMarketInfo {
+        is_initialized: true,
+        owner_wallet_address: admin,
+        treasury_wallet_address: Pubkey::new_unique(),
+        spl_treasury_wallet_address: Pubkey::new_unique(),
+        auction_fee_basis_points: 250,
+        raffle_fee_basis_points: 250,
+        featuring_fee_basis_points: 0,
+        randomness_oracle_program: Pubkey::default(),
+        default_extension: AuctionExtension {
+            window: 120,
+            length: 120,
+            max_total: 0,
+        },
+        min_auction_duration: 0,
+        max_auction_duration: 604800,
+    }
+    .try_to_vec()
+    .unwrap()
+}
+
+pub fn token_account(ledger: &mut Ledger, mint: Pubkey, owner: Pubkey, amount: u64) -> Pubkey {
+    let token_account = Pubkey::new_unique();
+    let mut token_data = vec![0; TokenAccount::LEN];
+    TokenAccount {
+        mint,
+        owner,
+        amount,
+        delegate: COption::None,
+        state: AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    }
+    .pack_into_slice(&mut token_data);
+    ledger.add(token_account, spl_token::id(), 1, token_data);
+    token_account
+}
+
+// a one of one nft held by `holder` in a verified `collection`,
+// returns its (token account, mint, metadata) keys
+pub fn nft(
+    ledger: &mut Ledger,
+    holder: Pubkey,
+    collection: Pubkey,
+    creator: Pubkey,
+) -> (Pubkey, Pubkey, Pubkey) {
+    let mint = Pubkey::new_unique();
+    let metadata = find_metadata_account(&mint).0;
+    let mut mint_data = vec![0; Mint::LEN];
+    Mint {
+        mint_authority: COption::None,
+        supply: 1,
+        decimals: 0,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut mint_data);
+    ledger.add(mint, spl_token::id(), 1, mint_data);
+    let token_account = token_account(ledger, mint, holder, 1);
+    let mut metadata_data = Metadata {
+        key: Key::MetadataV1,
+        update_authority: Pubkey::new_unique(),
+        mint,
+        data: Data {
+            name: "nft".to_string(),
+            symbol: "NFT".to_string(),
+            uri: String::new(),
+            seller_fee_basis_points: 500,
+            creators: Some(vec![Creator {
+                address: creator,
+                verified: true,
+                share: 100,
+            }]),
+        },
+        primary_sale_happened: true,
+        is_mutable: false,
+        edition_nonce: None,
+        token_standard: None,
+        collection: Some(Collection {
+            verified: true,
+            key: collection,
+        }),
+        uses: None,
+    }
+    .try_to_vec()
+    .unwrap();
+    metadata_data.resize(MAX_METADATA_LEN, 0);
+    ledger.add(metadata, mpl_token_metadata::id(), 1, metadata_data);
+    (token_account, mint, metadata)
+}
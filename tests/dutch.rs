@@ -0,0 +1,85 @@
+use marketplace::{
+    dutch,
+    state::{DecaySchedule, DutchAuctionOrder},
+};
+use solana_program::pubkey::Pubkey;
+
+// 1_000 down to 100 over 900 seconds, one lamport a second when linear
+fn order(decay: DecaySchedule, step_interval: u64) -> DutchAuctionOrder {
+    DutchAuctionOrder {
+        is_initialized: true,
+        owner_wallet_address: Pubkey::new_unique(),
+        token_account: Pubkey::new_unique(),
+        token_type: Pubkey::new_unique(),
+        start_price: 1_000,
+        floor_price: 100,
+        start_time: 10_000,
+        end_time: 10_900,
+        decay,
+        step_interval,
+    }
+}
+
+#[test]
+fn linear_price_falls_evenly() {
+    let order = order(DecaySchedule::Linear, 0);
+    assert_eq!(dutch::current_price(&order, 9_000), Ok(1_000));
+    assert_eq!(dutch::current_price(&order, 10_000), Ok(1_000));
+    assert_eq!(dutch::current_price(&order, 10_001), Ok(999));
+    assert_eq!(dutch::current_price(&order, 10_450), Ok(550));
+    assert_eq!(dutch::current_price(&order, 10_899), Ok(101));
+}
+
+#[test]
+fn linear_price_rounds_up() {
+    let mut order = order(DecaySchedule::Linear, 0);
+    order.end_time = order.start_time + 7;
+    // a seventh of the way in the 900 range has dropped 128.57, the buyer pays 872
+    assert_eq!(dutch::current_price(&order, order.start_time + 1), Ok(872));
+    assert_eq!(dutch::current_price(&order, order.start_time + 6), Ok(229));
+}
+
+#[test]
+fn stepwise_price_holds_between_steps() {
+    let order = order(DecaySchedule::Stepwise, 300);
+    assert_eq!(dutch::current_price(&order, 10_001), Ok(1_000));
+    assert_eq!(dutch::current_price(&order, 10_299), Ok(1_000));
+    assert_eq!(dutch::current_price(&order, 10_300), Ok(700));
+    assert_eq!(dutch::current_price(&order, 10_599), Ok(700));
+    assert_eq!(dutch::current_price(&order, 10_600), Ok(400));
+    assert_eq!(dutch::current_price(&order, 10_899), Ok(400));
+}
+
+#[test]
+fn price_is_clamped_to_the_floor() {
+    for decay in [DecaySchedule::Linear, DecaySchedule::Stepwise] {
+        let order = order(decay, 300);
+        for now in [10_900, 10_901, 20_000, u64::MAX] {
+            assert_eq!(dutch::current_price(&order, now), Ok(100));
+        }
+    }
+}
+
+#[test]
+fn price_never_leaves_the_schedule() {
+    for decay in [DecaySchedule::Linear, DecaySchedule::Stepwise] {
+        let order = order(decay, 7);
+        let mut last = order.start_price;
+        for now in order.start_time..=order.end_time + 10 {
+            let price = dutch::current_price(&order, now).unwrap();
+            assert!(price <= last && price >= order.floor_price);
+            last = price;
+        }
+        assert_eq!(last, order.floor_price);
+    }
+}
+
+#[test]
+fn large_prices_do_not_overflow() {
+    let mut order = order(DecaySchedule::Linear, 0);
+    order.start_price = u64::MAX;
+    order.floor_price = 0;
+    order.end_time = u64::MAX;
+    assert_eq!(dutch::current_price(&order, order.start_time), Ok(u64::MAX));
+    assert!(dutch::current_price(&order, u64::MAX - 1).unwrap() < u64::MAX / 2);
+}
@@ -0,0 +1,231 @@
+use marketplace::{
+    error::MarketError,
+    fees::{self, MAX_BASIS_POINTS},
+    state::{AuctionExtension, BidIncrement, IncrementKind, IncrementTier, MAX_INCREMENT_TIERS},
+};
+use solana_program::program_error::ProgramError;
+
+// small xorshift generator so the property checks are reproducible
+struct Rng(u64);
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    fn amount(&mut self) -> u64 {
+        // mix full-range values with small ones where rounding matters most
+        match self.next() % 4 {
+            0 => self.next(),
+            1 => self.next() % 1_000_000_000_000,
+            2 => self.next() % 10_000,
+            _ => u64::MAX - self.next() % 10_000,
+        }
+    }
+    fn basis_points(&mut self) -> u16 {
+        (self.next() % (MAX_BASIS_POINTS as u64 + 1)) as u16
+    }
+}
+
+const EDGE_AMOUNTS: [u64; 8] = [0, 1, 9_999, 10_000, 10_001, 39, u64::MAX - 1, u64::MAX];
+const EDGE_BASIS_POINTS: [u16; 6] = [0, 1, 250, 9_999, 10_000, 5_000];
+
+#[test]
+fn split_never_creates_or_loses_lamports() {
+    let mut rng = Rng(0x5eed_1234_abcd_0001);
+    for _ in 0..200_000 {
+        let amount = rng.amount();
+        let basis_points = rng.basis_points();
+        let (seller, fee) = fees::split_amount(amount, basis_points).unwrap();
+        assert_eq!(seller as u128 + fee as u128, amount as u128);
+        assert!(fee <= amount);
+    }
+    for amount in EDGE_AMOUNTS {
+        for basis_points in EDGE_BASIS_POINTS {
+            let (seller, fee) = fees::split_amount(amount, basis_points).unwrap();
+            assert_eq!(seller as u128 + fee as u128, amount as u128);
+        }
+    }
+}
+
+#[test]
+fn fee_rounds_down_exactly() {
+    let mut rng = Rng(0x0dd_c0ffee);
+    for _ in 0..200_000 {
+        let amount = rng.amount();
+        let basis_points = rng.basis_points();
+        let expected = amount as u128 * basis_points as u128 / 10_000;
+        assert_eq!(fees::fee_amount(amount, basis_points).unwrap() as u128, expected);
+    }
+    assert_eq!(fees::fee_amount(39, 250).unwrap(), 0);
+    assert_eq!(fees::fee_amount(40, 250).unwrap(), 1);
+    assert_eq!(fees::fee_amount(u64::MAX, 10_000).unwrap(), u64::MAX);
+}
+
+#[test]
+fn fee_is_monotonic_in_basis_points() {
+    let mut rng = Rng(0xfee5);
+    for _ in 0..20_000 {
+        let amount = rng.amount();
+        let low = rng.basis_points();
+        let high = low.max(rng.basis_points());
+        assert!(fees::fee_amount(amount, low).unwrap() <= fees::fee_amount(amount, high).unwrap());
+    }
+}
+
+#[test]
+fn fee_above_one_hundred_percent_is_rejected() {
+    assert_eq!(
+        fees::split_amount(1_000, MAX_BASIS_POINTS + 1),
+        Err(ProgramError::from(MarketError::InvalidFee))
+    );
+}
+
+#[test]
+fn increment_rounds_up() {
+    assert_eq!(fees::increment_amount(0, 500).unwrap(), 0);
+    assert_eq!(fees::increment_amount(1, 500).unwrap(), 1);
+    assert_eq!(fees::increment_amount(20, 500).unwrap(), 1);
+    assert_eq!(fees::increment_amount(21, 500).unwrap(), 2);
+    let mut rng = Rng(0xb1d);
+    for _ in 0..200_000 {
+        let amount = rng.amount();
+        let basis_points = rng.basis_points();
+        let increment = fees::increment_amount(amount, basis_points).unwrap() as u128;
+        let exact = amount as u128 * basis_points as u128;
+        assert!(increment * 10_000 >= exact);
+        assert!(increment * 10_000 < exact + 10_000);
+    }
+}
+
+#[test]
+fn min_next_bid_reports_overflow() {
+    let rule = fees::default_increment();
+    assert_eq!(fees::min_next_bid_for(&rule, 100).unwrap(), 105);
+    assert_eq!(
+        fees::min_next_bid_for(&rule, u64::MAX),
+        Err(ProgramError::from(MarketError::MathOverflow))
+    );
+}
+
+fn tiered(tiers: &[(u64, u64)]) -> BidIncrement {
+    let mut rule = BidIncrement {
+        kind: IncrementKind::Tiered,
+        basis_points: 0,
+        step: 0,
+        tier_count: tiers.len() as u8,
+        tiers: [IncrementTier::default(); MAX_INCREMENT_TIERS],
+    };
+    for (tier, &(threshold, step)) in rule.tiers.iter_mut().zip(tiers) {
+        *tier = IncrementTier { threshold, step };
+    }
+    rule
+}
+
+#[test]
+fn increment_rules_step_from_the_current_bid() {
+    let percent = fees::default_increment();
+    assert_eq!(fees::min_next_bid_for(&percent, 0).unwrap(), 0);
+    assert_eq!(fees::min_next_bid_for(&percent, 100).unwrap(), 105);
+    assert_eq!(fees::min_next_bid_for(&percent, 21).unwrap(), 23);
+
+    let absolute = BidIncrement {
+        kind: IncrementKind::Absolute,
+        step: 1_000,
+        ..fees::default_increment()
+    };
+    assert_eq!(fees::min_next_bid_for(&absolute, 5).unwrap(), 1_005);
+
+    let rule = tiered(&[(0, 10), (1_000, 50), (10_000, 500)]);
+    assert_eq!(fees::min_next_bid_for(&rule, 999).unwrap(), 1_009);
+    assert_eq!(fees::min_next_bid_for(&rule, 1_000).unwrap(), 1_050);
+    assert_eq!(fees::min_next_bid_for(&rule, 50_000).unwrap(), 50_500);
+    assert_eq!(
+        fees::min_next_bid_for(&absolute, u64::MAX),
+        Err(ProgramError::from(MarketError::MathOverflow))
+    );
+}
+
+#[test]
+fn malformed_increment_rules_are_rejected() {
+    let invalid = Err(ProgramError::from(MarketError::InvalidIncrement));
+    assert_eq!(fees::check_increment(&fees::default_increment()), Ok(()));
+    assert_eq!(fees::check_increment(&tiered(&[(0, 10), (1_000, 50)])), Ok(()));
+    for basis_points in [0, MAX_BASIS_POINTS + 1] {
+        let rule = BidIncrement {
+            basis_points,
+            ..fees::default_increment()
+        };
+        assert_eq!(fees::check_increment(&rule), invalid);
+    }
+    let absolute = BidIncrement {
+        kind: IncrementKind::Absolute,
+        ..fees::default_increment()
+    };
+    assert_eq!(fees::check_increment(&absolute), invalid);
+    assert_eq!(fees::check_increment(&tiered(&[])), invalid);
+    assert_eq!(fees::check_increment(&tiered(&[(5, 10)])), invalid);
+    assert_eq!(fees::check_increment(&tiered(&[(0, 10), (0, 20)])), invalid);
+    assert_eq!(fees::check_increment(&tiered(&[(0, 10), (100, 0)])), invalid);
+    let mut stray = tiered(&[(0, 10)]);
+    stray.tiers[2].step = 7;
+    assert_eq!(fees::check_increment(&stray), invalid);
+}
+
+#[test]
+fn malformed_extensions_are_rejected() {
+    let extension = |window, length, max_total| AuctionExtension {
+        window,
+        length,
+        max_total,
+    };
+    let invalid = Err(ProgramError::from(MarketError::InvalidExtension));
+    assert_eq!(fees::check_extension(&extension(120, 120, 0)), Ok(()));
+    assert_eq!(fees::check_extension(&extension(120, 120, 120)), Ok(()));
+    assert_eq!(fees::check_extension(&extension(0, 120, 0)), invalid);
+    assert_eq!(fees::check_extension(&extension(120, 0, 0)), invalid);
+    assert_eq!(fees::check_extension(&extension(120, 121, 120)), invalid);
+}
+
+#[test]
+fn proxy_bids_resolve_one_increment_over_the_loser() {
+    let rule = tiered(&[(0, 10)]);
+    // challenger's ceiling beats the leader's, wins one step over it
+    assert_eq!(fees::proxy_bid_price(&rule, 500, 300, 1_000).unwrap(), (510, true));
+    // capped at the challenger's own ceiling
+    assert_eq!(fees::proxy_bid_price(&rule, 500, 300, 505).unwrap(), (505, true));
+    // never below what the challenger explicitly bid
+    assert_eq!(fees::proxy_bid_price(&rule, 500, 800, 1_000).unwrap(), (800, true));
+    // the leader holds and is bid one step over the challenger
+    assert_eq!(fees::proxy_bid_price(&rule, 1_000, 300, 600).unwrap(), (610, false));
+    assert_eq!(fees::proxy_bid_price(&rule, 1_000, 300, 995).unwrap(), (1_000, false));
+    // ties go to the earlier bidder
+    assert_eq!(fees::proxy_bid_price(&rule, 1_000, 1_000, 1_000).unwrap(), (1_000, false));
+    assert_eq!(
+        fees::proxy_bid_price(&rule, u64::MAX - 1, 1, u64::MAX).unwrap(),
+        (u64::MAX, true)
+    );
+}
+
+#[test]
+fn royalties_come_out_of_what_the_seller_keeps() {
+    // 2.5% marketplace fee and a 5% royalty on a 1000 lamport sale
+    let (seller, fee) = fees::split_amount(1_000, 250).unwrap();
+    let royalty = fees::royalty_amount(1_000, fee, 500).unwrap();
+    assert_eq!((seller, fee, royalty), (975, 25, 50));
+    // never more than what is left after the fee
+    assert_eq!(fees::royalty_amount(1_000, 25, 10_000).unwrap(), 975);
+    assert_eq!(fees::royalty_amount(1_000, 1_000, 500).unwrap(), 0);
+    // shares round down, the dust stays with the seller
+    let shares: u64 = [34, 33, 33]
+        .iter()
+        .map(|&share| fees::creator_share(50, share).unwrap())
+        .sum();
+    assert_eq!(shares, 49);
+    assert_eq!(fees::creator_share(u64::MAX, 100).unwrap(), u64::MAX);
+    assert_eq!(
+        fees::creator_share(50, 101),
+        Err(ProgramError::from(MarketError::InvalidFee))
+    );
+}
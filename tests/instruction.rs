@@ -0,0 +1,61 @@
+use marketplace::instruction::MarketplaceInstruction;
+use solana_program::program_error::ProgramError;
+use spl_token::error::TokenError;
+
+fn sealed_auction_start(flag: Option<u8>) -> Result<MarketplaceInstruction, ProgramError> {
+    let mut data = vec![44];
+    for field in [1_000u64, 2_000, 3_000] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    data.extend(flag);
+    MarketplaceInstruction::unpack(&data)
+}
+
+fn is_second_price(flag: Option<u8>) -> bool {
+    match sealed_auction_start(flag) {
+        Ok(MarketplaceInstruction::SealedAuctionStart {
+            is_second_price, ..
+        }) => is_second_price,
+        _ => panic!("not a sealed auction start"),
+    }
+}
+
+#[test]
+fn sealed_auction_flag_defaults_to_first_price() {
+    assert!(!is_second_price(None));
+    assert!(!is_second_price(Some(0)));
+    assert!(is_second_price(Some(1)));
+}
+
+#[test]
+fn sealed_auction_flag_rejects_other_bytes() {
+    for flag in [2, 0xff] {
+        assert!(matches!(
+            sealed_auction_start(Some(flag)),
+            Err(error) if error == TokenError::InvalidInstruction.into()
+        ));
+    }
+}
+
+#[test]
+fn auction_start_rejects_short_data() {
+    let mut data = vec![4];
+    data.extend_from_slice(&1_000u64.to_le_bytes());
+    data.extend_from_slice(&[0; 4]);
+    for len in [1, 5, data.len()] {
+        assert!(matches!(
+            MarketplaceInstruction::unpack(&data[..len]),
+            Err(error) if error == TokenError::InvalidInstruction.into()
+        ));
+    }
+}
+
+#[test]
+fn place_bid_rejects_short_data() {
+    for data in [&[5][..], &[5, 0, 0, 0, 0]] {
+        assert!(matches!(
+            MarketplaceInstruction::unpack(data),
+            Err(error) if error == TokenError::InvalidInstruction.into()
+        ));
+    }
+}
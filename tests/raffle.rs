@@ -0,0 +1,656 @@
+mod common;
+
+use borsh::BorshSerialize;
+use common::{market_info, nft, pda, program_id, setup, token_account, wsol, Ledger, NOW, SLOT};
+use marketplace::{
+    error::MarketError,
+    randomness,
+    state::{
+        AllowlistEntry, AllowlistKind, FeaturedRaffles, RaffleOrder, RafflePrize, RaffleTicket,
+        RandomnessSource, TicketRange,
+    },
+};
+use solana_program::{
+    borsh::try_from_slice_unchecked, hash::hashv, pubkey::Pubkey, system_program,
+    sysvar::slot_hashes,
+};
+
+fn allowlist_entry(ledger: &mut Ledger, kind: AllowlistKind, address: Pubkey) -> Pubkey {
+    let entry = pda(&[b"allowlist", address.as_ref()]);
+    let entry_struct = AllowlistEntry {
+        is_initialized: true,
+        kind,
+        address,
+    };
+    ledger.add(entry, program_id(), 1, entry_struct.try_to_vec().unwrap());
+    entry
+}
+
+fn raffle_start_data(prize_count: u8) -> Vec<u8> {
+    let mut data = vec![9];
+    data.extend_from_slice(&100u64.to_le_bytes()); // ticket price
+    data.extend_from_slice(&(NOW as u64 + 3600).to_le_bytes());
+    data.extend_from_slice(&10u64.to_le_bytes()); // ticket supply
+    data.extend_from_slice(&[0; 32]); // seed commitment
+    data.push(0); // commit-reveal
+    data.extend_from_slice(&[0; 32]);
+    data.push(prize_count);
+    data
+}
+
+fn sol_raffle(owner: Pubkey, prize_token_account: Pubkey, tickets_sold: u64) -> RaffleOrder {
+    RaffleOrder {
+        is_initialized: true,
+        owner_wallet_address: owner,
+        time: NOW as u64 + 3600,
+        price: 100,
+        token_type: wsol(),
+        ticket_supply: 10,
+        is_featured: false,
+        seed_commitment: [0; 32],
+        randomness_source: RandomnessSource::CommitReveal,
+        randomness_account: Pubkey::default(),
+        is_drawn: false,
+        randomness: [0; 32],
+        draw_slot: 0,
+        tickets_sold,
+        buyer_count: 2,
+        min_tickets: 0,
+        max_tickets_per_wallet: 0,
+        tickets_refunded: 0,
+        proceeds_claimed: false,
+        is_cancelled: false,
+        settled_prizes: 0,
+        nft_mint: Pubkey::new_unique(),
+        prizes: vec![RafflePrize {
+            token_account: prize_token_account,
+            winner_wallet_address: Pubkey::default(),
+            is_claimed: false,
+        }],
+    }
+}
+
+#[test]
+fn cancelled_raffle_refunds_a_ticket_only_once() {
+    setup();
+    let mut ledger = Ledger::default();
+    let (admin, owner, buyer) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let (raffle, prize_token_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let token_program = spl_token::id();
+    let config = pda(&[b"black_market_info"]);
+    let prize_pda = pda(&[b"seeds_for_pda"]);
+    let sol_escrow = pda(&[b"seeds_for_pda$0!"]);
+    let ticket = pda(&[b"raffle_ticket", raffle.as_ref(), buyer.as_ref()]);
+    ledger.wallet(admin, 0);
+    ledger.wallet(owner, 0);
+    ledger.wallet(buyer, 0);
+    ledger.wallet(prize_pda, 0);
+    // the escrow also holds other buyers' tickets
+    ledger.wallet(sol_escrow, 500);
+    ledger.wallet(system_program::id(), 0);
+    ledger.add(token_program, Pubkey::default(), 0, vec![]);
+    ledger.add(prize_token_account, token_program, 0, vec![]);
+    ledger.add(config, program_id(), 1, market_info(admin));
+    let mut raffle_data = sol_raffle(owner, prize_token_account, 5)
+        .try_to_vec()
+        .unwrap();
+    raffle_data.resize(1024, 0);
+    ledger.add(raffle, program_id(), 1, raffle_data);
+    let ticket_struct = RaffleTicket {
+        is_initialized: true,
+        raffle_account: raffle,
+        buyer_wallet_address: buyer,
+        ticket_count: 2,
+        ranges: vec![],
+    };
+    ledger.add(ticket, program_id(), 7, ticket_struct.try_to_vec().unwrap());
+
+    let cancel = [
+        (admin, true),
+        (raffle, false),
+        (owner, false),
+        (config, false),
+        (prize_pda, false),
+        (token_program, false),
+        (prize_token_account, false),
+        (sol_escrow, false),
+        (system_program::id(), false),
+    ];
+    ledger.process(&cancel, &[40]).unwrap();
+
+    let refund = [
+        (buyer, true),
+        (raffle, false),
+        (ticket, false),
+        (owner, false),
+        (sol_escrow, false),
+        (system_program::id(), false),
+    ];
+    ledger.process(&refund, &[38]).unwrap();
+    assert_eq!(ledger.lamports(&buyer), 207);
+    assert_eq!(ledger.lamports(&sol_escrow), 300);
+    // the drained ticket is still around for the rest of the transaction
+    assert!(ledger.process(&refund, &[38]).is_err());
+    assert_eq!(ledger.lamports(&buyer), 207);
+    assert_eq!(ledger.lamports(&sol_escrow), 300);
+}
+
+// a holder and an empty raffle account to list from, returns (holder, raffle)
+fn raffle_start_ledger(ledger: &mut Ledger) -> (Pubkey, Pubkey) {
+    let (holder, raffle) = (Pubkey::new_unique(), Pubkey::new_unique());
+    ledger.wallet(holder, 0);
+    ledger.add(spl_token::id(), Pubkey::default(), 0, vec![]);
+    ledger.add(wsol(), spl_token::id(), 0, vec![]);
+    ledger.add(raffle, program_id(), 1, vec![0; 1024]);
+    (holder, raffle)
+}
+
+// RaffleStart accounts for prizes given as (token, mint, metadata, allowlist entry)
+fn raffle_start_accounts(
+    holder: Pubkey,
+    raffle: Pubkey,
+    prizes: &[(Pubkey, Pubkey, Pubkey, Pubkey)],
+) -> Vec<(Pubkey, bool)> {
+    let (first_token, first_mint, first_metadata, first_entry) = prizes[0];
+    let mut accounts = vec![
+        (holder, true),
+        (first_token, false),
+        (raffle, false),
+        (spl_token::id(), false),
+        (wsol(), false),
+        (first_metadata, false),
+        (first_mint, false),
+        (first_entry, false),
+    ];
+    for (token, mint, metadata, entry) in &prizes[1..] {
+        accounts.extend([
+            (*token, false),
+            (*mint, false),
+            (*metadata, false),
+            (*entry, false),
+        ]);
+    }
+    accounts
+}
+
+#[test]
+fn raffle_start_reads_the_featured_account_only_when_passed() {
+    setup();
+    for is_featured in [false, true] {
+        let mut ledger = Ledger::default();
+        let (holder, raffle) = raffle_start_ledger(&mut ledger);
+        let (collection, creator) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let featured = Pubkey::new_unique();
+        let featured_struct = FeaturedRaffles {
+            is_initialized: false,
+            raffle_account: Pubkey::default(),
+            is_featured: false,
+        };
+        ledger.add(
+            featured,
+            program_id(),
+            1,
+            featured_struct.try_to_vec().unwrap(),
+        );
+        // the second prize is approved through its creator rather than the collection
+        let entry = allowlist_entry(&mut ledger, AllowlistKind::Collection, collection);
+        let creator_entry = allowlist_entry(&mut ledger, AllowlistKind::Creator, creator);
+        let (first_token, first_mint, first_metadata) =
+            nft(&mut ledger, holder, collection, Pubkey::new_unique());
+        let (second_token, second_mint, second_metadata) =
+            nft(&mut ledger, holder, collection, creator);
+        let mut accounts = raffle_start_accounts(
+            holder,
+            raffle,
+            &[
+                (first_token, first_mint, first_metadata, entry),
+                (second_token, second_mint, second_metadata, creator_entry),
+            ],
+        );
+        if is_featured {
+            accounts.push((featured, false));
+        }
+        ledger.process(&accounts, &raffle_start_data(2)).unwrap();
+        let raffle_struct: RaffleOrder = try_from_slice_unchecked(ledger.data(&raffle)).unwrap();
+        assert_eq!(raffle_struct.is_featured, is_featured);
+        assert_eq!(raffle_struct.prizes.len(), 2);
+        let featured_struct: FeaturedRaffles =
+            try_from_slice_unchecked(ledger.data(&featured)).unwrap();
+        assert_eq!(featured_struct.is_initialized, is_featured);
+    }
+}
+
+#[test]
+fn raffle_start_checks_the_allowlist_for_every_prize() {
+    setup();
+    let mut ledger = Ledger::default();
+    let (holder, raffle) = raffle_start_ledger(&mut ledger);
+    let (collection, unlisted_collection, other_collection) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let entry = allowlist_entry(&mut ledger, AllowlistKind::Collection, collection);
+    // a live entry, but for a collection the second prize is not part of
+    let other_entry = allowlist_entry(&mut ledger, AllowlistKind::Collection, other_collection);
+    let (first_token, first_mint, first_metadata) =
+        nft(&mut ledger, holder, collection, Pubkey::new_unique());
+    let (second_token, second_mint, second_metadata) = nft(
+        &mut ledger,
+        holder,
+        unlisted_collection,
+        Pubkey::new_unique(),
+    );
+    let accounts = raffle_start_accounts(
+        holder,
+        raffle,
+        &[
+            (first_token, first_mint, first_metadata, entry),
+            (second_token, second_mint, second_metadata, other_entry),
+        ],
+    );
+    assert_eq!(
+        ledger.process(&accounts, &raffle_start_data(2)),
+        Err(MarketError::NotAllowlisted.into())
+    );
+}
+
+#[test]
+fn raffle_start_keeps_every_prize_in_the_first_prizes_collection() {
+    setup();
+    let mut ledger = Ledger::default();
+    let (holder, raffle) = raffle_start_ledger(&mut ledger);
+    let (collection, other_collection) = (Pubkey::new_unique(), Pubkey::new_unique());
+    // both approved, but royalties only reach the first prize's creators
+    let entry = allowlist_entry(&mut ledger, AllowlistKind::Collection, collection);
+    let other_entry = allowlist_entry(&mut ledger, AllowlistKind::Collection, other_collection);
+    let (first_token, first_mint, first_metadata) =
+        nft(&mut ledger, holder, collection, Pubkey::new_unique());
+    let (second_token, second_mint, second_metadata) =
+        nft(&mut ledger, holder, other_collection, Pubkey::new_unique());
+    let accounts = raffle_start_accounts(
+        holder,
+        raffle,
+        &[
+            (first_token, first_mint, first_metadata, entry),
+            (second_token, second_mint, second_metadata, other_entry),
+        ],
+    );
+    assert_eq!(
+        ledger.process(&accounts, &raffle_start_data(2)),
+        Err(MarketError::PrizeCollectionMismatch.into())
+    );
+}
+
+// slot hashes sysvar data, (slot, hash) entries newest first
+fn slot_hashes_data(entries: &[(u64, [u8; 32])]) -> Vec<u8> {
+    let mut data = (entries.len() as u64).to_le_bytes().to_vec();
+    for (slot, hash) in entries {
+        data.extend_from_slice(&slot.to_le_bytes());
+        data.extend_from_slice(hash);
+    }
+    data
+}
+
+// a sol commit-reveal raffle whose sales closed with one buyer holding
+// every ticket, returns (owner, buyer, raffle, config, ticket)
+fn closed_commit_reveal_raffle(
+    ledger: &mut Ledger,
+    seed: [u8; 32],
+) -> (Pubkey, Pubkey, Pubkey, Pubkey, Pubkey) {
+    let (admin, owner, buyer, raffle) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let config = pda(&[b"black_market_info"]);
+    let ticket = pda(&[b"raffle_ticket", raffle.as_ref(), buyer.as_ref()]);
+    ledger.wallet(admin, 0);
+    ledger.wallet(owner, 0);
+    ledger.wallet(buyer, 0);
+    ledger.wallet(pda(&[b"seeds_for_pda$0!"]), 300);
+    ledger.wallet(system_program::id(), 0);
+    ledger.add(config, program_id(), 1, market_info(admin));
+    let mut raffle_struct = sol_raffle(owner, Pubkey::new_unique(), 3);
+    raffle_struct.time = NOW as u64 - 1;
+    raffle_struct.seed_commitment = hashv(&[&seed]).to_bytes();
+    let mut raffle_data = raffle_struct.try_to_vec().unwrap();
+    raffle_data.resize(1024, 0);
+    ledger.add(raffle, program_id(), 1, raffle_data);
+    let ticket_struct = RaffleTicket {
+        is_initialized: true,
+        raffle_account: raffle,
+        buyer_wallet_address: buyer,
+        ticket_count: 3,
+        ranges: vec![TicketRange { start: 0, end: 3 }],
+    };
+    ledger.add(ticket, program_id(), 1, ticket_struct.try_to_vec().unwrap());
+    (owner, buyer, raffle, config, ticket)
+}
+
+fn set_draw_slot(ledger: &mut Ledger, raffle: &Pubkey, draw_slot: u64) {
+    let mut raffle_struct: RaffleOrder = try_from_slice_unchecked(ledger.data(raffle)).unwrap();
+    raffle_struct.draw_slot = draw_slot;
+    raffle_struct
+        .serialize(&mut &mut ledger.data_mut(raffle)[..])
+        .unwrap();
+}
+
+#[test]
+fn commit_reveal_draw_uses_a_slot_fixed_after_sales_close() {
+    setup();
+    let mut ledger = Ledger::default();
+    let seed = [9; 32];
+    let (owner, _, raffle, config, _) = closed_commit_reveal_raffle(&mut ledger, seed);
+    ledger.add(
+        slot_hashes::id(),
+        Pubkey::default(),
+        1,
+        slot_hashes_data(&[(SLOT - 1, [3; 32])]),
+    );
+    let accounts = [
+        (owner, true),
+        (raffle, false),
+        (slot_hashes::id(), false),
+        (config, false),
+    ];
+    let draw = |seed: [u8; 32]| [&[35][..], &seed].concat();
+
+    // the first call only fixes a slot nobody has seen the hash of
+    ledger.process(&accounts, &draw(seed)).unwrap();
+    let raffle_struct: RaffleOrder = try_from_slice_unchecked(ledger.data(&raffle)).unwrap();
+    assert!(raffle_struct.draw_slot > SLOT);
+    assert!(!raffle_struct.is_drawn);
+    assert_eq!(
+        ledger.process(&accounts, &draw(seed)),
+        Err(MarketError::RandomnessNotReady.into())
+    );
+
+    // the chain moves past the draw slot, which was skipped
+    set_draw_slot(&mut ledger, &raffle, SLOT - 5);
+    *ledger.data_mut(&slot_hashes::id()) = slot_hashes_data(&[
+        (SLOT - 1, [2; 32]),
+        (SLOT - 4, [1; 32]),
+        (SLOT - 6, [0; 32]),
+    ]);
+    assert_eq!(
+        ledger.process(&accounts, &draw([8; 32])),
+        Err(MarketError::SeedMismatch.into())
+    );
+    ledger.process(&accounts, &draw(seed)).unwrap();
+    let raffle_struct: RaffleOrder = try_from_slice_unchecked(ledger.data(&raffle)).unwrap();
+    assert!(raffle_struct.is_drawn);
+    assert_eq!(
+        raffle_struct.randomness,
+        hashv(&[&seed, &[1; 32], raffle.as_ref()]).to_bytes()
+    );
+}
+
+#[test]
+fn unrevealed_commit_reveal_raffle_refunds_buyers() {
+    setup();
+    let mut ledger = Ledger::default();
+    let seed = [9; 32];
+    let (owner, buyer, raffle, config, ticket) = closed_commit_reveal_raffle(&mut ledger, seed);
+    let sol_escrow = pda(&[b"seeds_for_pda$0!"]);
+    ledger.add(
+        slot_hashes::id(),
+        Pubkey::default(),
+        1,
+        slot_hashes_data(&[(SLOT - 1, [2; 32])]),
+    );
+    let refund = [
+        (buyer, true),
+        (raffle, false),
+        (ticket, false),
+        (owner, false),
+        (sol_escrow, false),
+        (system_program::id(), false),
+    ];
+    // the draw slot is fixed and the reveal is still open
+    set_draw_slot(&mut ledger, &raffle, SLOT - 10);
+    assert_eq!(
+        ledger.process(&refund, &[38]),
+        Err(MarketError::RefundNotAvailable.into())
+    );
+
+    // the owner sat on the seed until the slot's hash left the sysvar
+    set_draw_slot(&mut ledger, &raffle, SLOT - 600);
+    let draw = [
+        (owner, true),
+        (raffle, false),
+        (slot_hashes::id(), false),
+        (config, false),
+    ];
+    assert_eq!(
+        ledger.process(&draw, &[&[35][..], &seed].concat()),
+        Err(MarketError::DrawWindowMissed.into())
+    );
+    ledger.process(&refund, &[38]).unwrap();
+    assert_eq!(ledger.lamports(&buyer), 301);
+    assert_eq!(ledger.lamports(&sol_escrow), 0);
+}
+
+#[cfg(feature = "local-randomness")]
+#[test]
+fn local_draw_checks_the_seed_commitment() {
+    setup();
+    let mut ledger = Ledger::default();
+    let (admin, owner, raffle) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let (config, randomness_account) = (pda(&[b"black_market_info"]), Pubkey::new_unique());
+    let seed = [4; 32];
+    ledger.wallet(admin, 0);
+    ledger.wallet(owner, 0);
+    ledger.wallet(randomness_account, 0);
+    ledger.add(config, program_id(), 1, market_info(admin));
+    let mut raffle_struct = sol_raffle(owner, Pubkey::new_unique(), 3);
+    raffle_struct.time = NOW as u64 - 1;
+    raffle_struct.randomness_source = RandomnessSource::Local;
+    raffle_struct.seed_commitment = hashv(&[&seed]).to_bytes();
+    let mut raffle_data = raffle_struct.try_to_vec().unwrap();
+    raffle_data.resize(1024, 0);
+    ledger.add(raffle, program_id(), 1, raffle_data);
+    let accounts = [
+        (owner, true),
+        (raffle, false),
+        (randomness_account, false),
+        (config, false),
+    ];
+    let mut draw = vec![35];
+    draw.extend_from_slice(&[5; 32]);
+    assert_eq!(
+        ledger.process(&accounts, &draw),
+        Err(MarketError::SeedMismatch.into())
+    );
+    let mut draw = vec![35];
+    draw.extend_from_slice(&seed);
+    ledger.process(&accounts, &draw).unwrap();
+    let raffle_struct: RaffleOrder = try_from_slice_unchecked(ledger.data(&raffle)).unwrap();
+    assert_eq!(
+        raffle_struct.randomness,
+        hashv(&[&seed, raffle.as_ref()]).to_bytes()
+    );
+}
+
+#[test]
+fn spl_refund_only_pays_out_of_the_raffles_own_escrow() {
+    setup();
+    let mut ledger = Ledger::default();
+    let (owner, buyer, raffle) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let (mint, valuable_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let prize_pda = pda(&[b"seeds_for_pda"]);
+    let ticket = pda(&[b"raffle_ticket", raffle.as_ref(), buyer.as_ref()]);
+    ledger.wallet(owner, 0);
+    ledger.wallet(buyer, 0);
+    ledger.wallet(prize_pda, 0);
+    ledger.add(spl_token::id(), Pubkey::default(), 0, vec![]);
+    ledger.add(mint, spl_token::id(), 1, vec![]);
+    ledger.add(valuable_mint, spl_token::id(), 1, vec![]);
+    // the pda also escrows other raffles and auctions
+    let escrow = token_account(&mut ledger, mint, prize_pda, 200);
+    let other_escrow = token_account(&mut ledger, valuable_mint, prize_pda, 1_000);
+    let destination = token_account(&mut ledger, mint, buyer, 0);
+    let valuable_destination = token_account(&mut ledger, valuable_mint, buyer, 0);
+    let mut raffle_struct = sol_raffle(owner, Pubkey::new_unique(), 2);
+    raffle_struct.token_type = mint;
+    raffle_struct.is_cancelled = true;
+    let mut raffle_data = raffle_struct.try_to_vec().unwrap();
+    raffle_data.resize(1024, 0);
+    ledger.add(raffle, program_id(), 1, raffle_data);
+    let ticket_struct = RaffleTicket {
+        is_initialized: true,
+        raffle_account: raffle,
+        buyer_wallet_address: buyer,
+        ticket_count: 2,
+        ranges: vec![TicketRange { start: 0, end: 2 }],
+    };
+    ledger.add(ticket, program_id(), 7, ticket_struct.try_to_vec().unwrap());
+    let refund = |escrow, escrow_mint, destination| {
+        [
+            (buyer, true),
+            (raffle, false),
+            (ticket, false),
+            (owner, false),
+            (prize_pda, false),
+            (escrow, false),
+            (escrow_mint, false),
+            (spl_token::id(), false),
+            (destination, false),
+        ]
+    };
+
+    assert_eq!(
+        ledger.process(
+            &refund(other_escrow, valuable_mint, valuable_destination),
+            &[38]
+        ),
+        Err(MarketError::PdaError.into())
+    );
+    assert_eq!(
+        ledger.process(&refund(other_escrow, mint, destination), &[38]),
+        Err(MarketError::PdaError.into())
+    );
+    assert_eq!(
+        ledger.process(&refund(escrow, mint, valuable_destination), &[38]),
+        Err(MarketError::ValueMisMatch.into())
+    );
+    ledger
+        .process(&refund(escrow, mint, destination), &[38])
+        .unwrap();
+    // the ticket's rent goes back to the buyer once refunded
+    assert_eq!(ledger.lamports(&ticket), 0);
+}
+
+// the ticket a tier's first draw lands on, the way SettleRaffleWinners draws it
+fn first_draw(randomness: &[u8; 32], tier: u64, tickets_sold: u64) -> u64 {
+    let tier_randomness = hashv(&[randomness, &tier.to_le_bytes(), &0u64.to_le_bytes()]);
+    randomness::ticket_index(&tier_randomness.to_bytes(), tickets_sold).unwrap()
+}
+
+// a drawn raffle of `prize_count` tiers with two buyers of two tickets each,
+// whose first draws give the top tier to the first buyer and the next to the
+// second. returns (raffle, [(buyer, ticket account)])
+fn drawn_raffle(ledger: &mut Ledger, prize_count: usize) -> (Pubkey, Vec<(Pubkey, Pubkey)>) {
+    let raffle = Pubkey::new_unique();
+    let randomness = (0u64..)
+        .map(|i| hashv(&[&i.to_le_bytes()]).to_bytes())
+        .find(|randomness| first_draw(randomness, 0, 4) < 2 && first_draw(randomness, 1, 4) >= 2)
+        .unwrap();
+    let mut raffle_struct = sol_raffle(Pubkey::new_unique(), Pubkey::new_unique(), 4);
+    raffle_struct.time = NOW as u64 - 1;
+    raffle_struct.is_drawn = true;
+    raffle_struct.randomness = randomness;
+    raffle_struct.prizes = (0..prize_count)
+        .map(|_| RafflePrize {
+            token_account: Pubkey::new_unique(),
+            winner_wallet_address: Pubkey::default(),
+            is_claimed: false,
+        })
+        .collect();
+    let mut raffle_data = raffle_struct.try_to_vec().unwrap();
+    raffle_data.resize(1024, 0);
+    ledger.add(raffle, program_id(), 1, raffle_data);
+    let buyers = (0..2)
+        .map(|i| {
+            let buyer = Pubkey::new_unique();
+            let ticket = pda(&[b"raffle_ticket", raffle.as_ref(), buyer.as_ref()]);
+            let ticket_struct = RaffleTicket {
+                is_initialized: true,
+                raffle_account: raffle,
+                buyer_wallet_address: buyer,
+                ticket_count: 2,
+                ranges: vec![TicketRange {
+                    start: i * 2,
+                    end: i * 2 + 2,
+                }],
+            };
+            ledger.add(ticket, program_id(), 1, ticket_struct.try_to_vec().unwrap());
+            (buyer, ticket)
+        })
+        .collect();
+    (raffle, buyers)
+}
+
+#[test]
+fn settling_leaves_the_tiers_past_the_buyer_count_unawarded() {
+    setup();
+    let mut ledger = Ledger::default();
+    let (raffle, buyers) = drawn_raffle(&mut ledger, 3);
+    let accounts = [(raffle, false), (buyers[0].1, false), (buyers[1].1, false)];
+    ledger.process(&accounts, &[37]).unwrap();
+    let raffle_struct: RaffleOrder = try_from_slice_unchecked(ledger.data(&raffle)).unwrap();
+    assert_eq!(raffle_struct.settled_prizes, 3);
+    assert_eq!(raffle_struct.prizes[0].winner_wallet_address, buyers[0].0);
+    assert_eq!(raffle_struct.prizes[1].winner_wallet_address, buyers[1].0);
+    // two buyers can't win three prizes, the last one goes back to the owner
+    assert_eq!(
+        raffle_struct.prizes[2].winner_wallet_address,
+        Pubkey::default()
+    );
+    assert_eq!(
+        ledger.process(&accounts, &[37]),
+        Err(MarketError::ValueMisMatch.into())
+    );
+}
+
+#[test]
+fn settling_stops_at_a_tier_whose_ticket_account_is_missing() {
+    setup();
+    let mut ledger = Ledger::default();
+    let (raffle, buyers) = drawn_raffle(&mut ledger, 3);
+    let first_only = [(raffle, false), (buyers[0].1, false)];
+    ledger.process(&first_only, &[37]).unwrap();
+    let raffle_struct: RaffleOrder = try_from_slice_unchecked(ledger.data(&raffle)).unwrap();
+    assert_eq!(raffle_struct.settled_prizes, 1);
+    assert_eq!(raffle_struct.prizes[0].winner_wallet_address, buyers[0].0);
+    assert_eq!(
+        raffle_struct.prizes[1].winner_wallet_address,
+        Pubkey::default()
+    );
+    // nothing more can be settled until the second tier's holder is passed
+    assert_eq!(
+        ledger.process(&first_only, &[37]),
+        Err(MarketError::ValueMisMatch.into())
+    );
+
+    ledger
+        .process(&[(raffle, false), (buyers[1].1, false)], &[37])
+        .unwrap();
+    let raffle_struct: RaffleOrder = try_from_slice_unchecked(ledger.data(&raffle)).unwrap();
+    assert_eq!(raffle_struct.settled_prizes, 3);
+    assert_eq!(raffle_struct.prizes[0].winner_wallet_address, buyers[0].0);
+    assert_eq!(raffle_struct.prizes[1].winner_wallet_address, buyers[1].0);
+}
@@ -0,0 +1,243 @@
+use borsh::BorshSerialize;
+use marketplace::{
+    error::MarketError,
+    randomness::{self, CommitRevealProvider, OracleProvider, RandomnessProvider},
+    state::OracleRandomness,
+};
+use solana_program::{
+    account_info::AccountInfo, hash::hashv, program_error::ProgramError, pubkey::Pubkey,
+    sysvar::slot_hashes,
+};
+
+// the oracle program is never invoked, the provider only reads the result
+// account it owns
+struct MockOracle {
+    program: Pubkey,
+    key: Pubkey,
+    lamports: u64,
+    data: Vec<u8>,
+}
+impl MockOracle {
+    fn new(requester: Pubkey, is_fulfilled: bool, fulfilled_at: u64) -> Self {
+        let result = OracleRandomness {
+            is_fulfilled,
+            requester,
+            fulfilled_at,
+            randomness: [7; 32],
+        };
+        MockOracle {
+            program: Pubkey::new_unique(),
+            key: Pubkey::new_unique(),
+            lamports: 1,
+            data: result.try_to_vec().unwrap(),
+        }
+    }
+    fn randomness(
+        &mut self,
+        oracle_program: Pubkey,
+        raffle: &Pubkey,
+        not_before: u64,
+    ) -> Result<[u8; 32], ProgramError> {
+        let info = AccountInfo::new(
+            &self.key,
+            false,
+            false,
+            &mut self.lamports,
+            &mut self.data,
+            &self.program,
+            false,
+            0,
+        );
+        OracleProvider {
+            oracle_program,
+            randomness_account_info: &info,
+            not_before,
+        }
+        .randomness(raffle)
+    }
+}
+
+fn slot_hash(key: Pubkey, mut data: Vec<u8>, slot: u64) -> Result<[u8; 32], ProgramError> {
+    let (owner, mut lamports) = (Pubkey::default(), 1);
+    let info = AccountInfo::new(
+        &key,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &owner,
+        false,
+        0,
+    );
+    randomness::slot_hash_at(&info, slot)
+}
+
+// slots 44, 42 and 41 were produced, 43 was skipped
+fn slot_hashes_data() -> Vec<u8> {
+    let mut data = 3u64.to_le_bytes().to_vec();
+    for (slot, hash) in [(44u64, [4; 32]), (42, [2; 32]), (41, [1; 32])] {
+        data.extend_from_slice(&slot.to_le_bytes());
+        data.extend_from_slice(&hash);
+    }
+    data
+}
+
+#[test]
+fn oracle_result_is_bound_to_the_raffle() {
+    let raffle = Pubkey::new_unique();
+    let mut oracle = MockOracle::new(raffle, true, 100);
+    let program = oracle.program;
+    assert_eq!(
+        oracle.randomness(program, &raffle, 99),
+        Ok(hashv(&[&[7; 32], raffle.as_ref()]).to_bytes())
+    );
+}
+
+#[test]
+fn oracle_result_must_be_owned_by_the_configured_program() {
+    let raffle = Pubkey::new_unique();
+    let mut oracle = MockOracle::new(raffle, true, 100);
+    assert_eq!(
+        oracle.randomness(Pubkey::new_unique(), &raffle, 99),
+        Err(ProgramError::IncorrectProgramId)
+    );
+    // an unset oracle never matches, even a result owned by the default key
+    oracle.program = Pubkey::default();
+    assert_eq!(
+        oracle.randomness(Pubkey::default(), &raffle, 99),
+        Err(ProgramError::IncorrectProgramId)
+    );
+}
+
+#[test]
+fn oracle_result_for_another_raffle_is_rejected() {
+    let mut oracle = MockOracle::new(Pubkey::new_unique(), true, 100);
+    let program = oracle.program;
+    assert_eq!(
+        oracle.randomness(program, &Pubkey::new_unique(), 99),
+        Err(MarketError::ValueMisMatch.into())
+    );
+}
+
+#[test]
+fn oracle_result_must_be_fulfilled_after_sales_close() {
+    let raffle = Pubkey::new_unique();
+    let mut pending = MockOracle::new(raffle, false, 100);
+    let program = pending.program;
+    assert_eq!(
+        pending.randomness(program, &raffle, 99),
+        Err(MarketError::RandomnessNotReady.into())
+    );
+    let mut early = MockOracle::new(raffle, true, 100);
+    let program = early.program;
+    assert_eq!(
+        early.randomness(program, &raffle, 100),
+        Err(MarketError::RandomnessNotReady.into())
+    );
+}
+
+#[test]
+fn commit_reveal_mixes_seed_slot_hash_and_raffle() {
+    let raffle = Pubkey::new_unique();
+    let provider = CommitRevealProvider {
+        seed: [1; 32],
+        slot_hash: [2; 32],
+    };
+    let randomness = provider.randomness(&raffle).unwrap();
+    assert_eq!(
+        randomness,
+        hashv(&[&[1; 32], &[2; 32], raffle.as_ref()]).to_bytes()
+    );
+    assert_ne!(
+        randomness,
+        provider.randomness(&Pubkey::new_unique()).unwrap()
+    );
+}
+
+#[cfg(feature = "local-randomness")]
+#[test]
+fn deterministic_provider_only_depends_on_seed_and_raffle() {
+    use marketplace::randomness::DeterministicProvider;
+    let raffle = Pubkey::new_unique();
+    let provider = DeterministicProvider { seed: [3; 32] };
+    assert_eq!(
+        provider.randomness(&raffle),
+        Ok(hashv(&[&[3; 32], raffle.as_ref()]).to_bytes())
+    );
+    assert_eq!(provider.randomness(&raffle), provider.randomness(&raffle));
+}
+
+#[test]
+fn ticket_index_stays_below_the_tickets_sold() {
+    let mut randomness = [0; 32];
+    randomness[..8].copy_from_slice(&1_000_003u64.to_le_bytes());
+    assert_eq!(randomness::ticket_index(&randomness, 1_000), Ok(3));
+    assert_eq!(randomness::ticket_index(&randomness, 1), Ok(0));
+    assert_eq!(randomness::ticket_index(&[0xff; 32], 7), Ok(u64::MAX % 7));
+    for total_tickets in 1..500u64 {
+        let randomness = hashv(&[&total_tickets.to_le_bytes()]).to_bytes();
+        assert!(randomness::ticket_index(&randomness, total_tickets).unwrap() < total_tickets);
+    }
+}
+
+#[test]
+fn ticket_index_needs_a_ticket_sold() {
+    assert_eq!(
+        randomness::ticket_index(&[1; 32], 0),
+        Err(MarketError::InvalidInstruction.into())
+    );
+}
+
+#[test]
+fn slot_hash_at_reads_the_requested_slot() {
+    assert_eq!(
+        slot_hash(slot_hashes::id(), slot_hashes_data(), 42),
+        Ok([2; 32])
+    );
+    assert_eq!(
+        slot_hash(slot_hashes::id(), slot_hashes_data(), 44),
+        Ok([4; 32])
+    );
+}
+
+#[test]
+fn slot_hash_at_skips_to_the_next_produced_slot() {
+    assert_eq!(
+        slot_hash(slot_hashes::id(), slot_hashes_data(), 43),
+        Ok([4; 32])
+    );
+}
+
+#[test]
+fn slot_hash_at_waits_for_the_slot() {
+    assert_eq!(
+        slot_hash(slot_hashes::id(), slot_hashes_data(), 45),
+        Err(MarketError::RandomnessNotReady.into())
+    );
+    assert_eq!(
+        slot_hash(slot_hashes::id(), 0u64.to_le_bytes().to_vec(), 1),
+        Err(MarketError::RandomnessNotReady.into())
+    );
+}
+
+#[test]
+fn slot_hash_at_only_trusts_the_sysvar() {
+    assert_eq!(
+        slot_hash(Pubkey::new_unique(), slot_hashes_data(), 42),
+        Err(ProgramError::InvalidArgument)
+    );
+}
+
+#[test]
+fn slot_hash_at_rejects_short_data() {
+    let mut short = slot_hashes_data();
+    short.pop();
+    assert_eq!(
+        slot_hash(slot_hashes::id(), short, 41),
+        Err(ProgramError::InvalidAccountData)
+    );
+    assert_eq!(
+        slot_hash(slot_hashes::id(), vec![0; 7], 41),
+        Err(ProgramError::InvalidAccountData)
+    );
+}